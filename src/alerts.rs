@@ -0,0 +1,274 @@
+use crate::config::AlertsConfig;
+use crate::SystemStats;
+use log::{debug, info, warn};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const REQUEST_TIMEOUT_SECS: &str = "10";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Metric {
+    Temp,
+    Ram,
+    Disk,
+}
+
+struct Rule {
+    metric: Metric,
+    threshold: f64,
+    /// Whether the last alert sent was for crossing the threshold.
+    active: bool,
+    /// Since when the value has been on the other side of the threshold
+    /// than `active` says.
+    pending_since: Option<Instant>,
+}
+
+/// POSTs a JSON message to `webhook_url` when a stat crosses its threshold
+/// and again when it is back below. A crossing or clearing only counts once
+/// it has lasted `debounce_secs`, so a value hovering around the threshold
+/// does not send an alert on every refresh. The requests are made by `curl`
+/// on a background thread, so a slow webhook never holds up the display.
+pub struct AlertNotifier {
+    rules: Vec<Rule>,
+    debounce: Duration,
+    fahrenheit: bool,
+    sender: Sender<String>,
+}
+
+impl AlertNotifier {
+    pub fn start(alerts_config: &AlertsConfig, url: &str, fahrenheit: bool) -> Self {
+        let rules = [
+            (Metric::Temp, alerts_config.temp_above.map(f64::from)),
+            (Metric::Ram, alerts_config.ram_above),
+            (Metric::Disk, alerts_config.disk_above),
+        ]
+        .into_iter()
+        .filter_map(|(metric, threshold)| {
+            Some(Rule {
+                metric,
+                threshold: threshold?,
+                active: false,
+                pending_since: None,
+            })
+        })
+        .collect();
+        let (sender, receiver) = mpsc::channel();
+        let url = url.to_string();
+        thread::spawn(move || run(&url, receiver));
+        info!("Sending threshold alerts to the configured webhook");
+
+        AlertNotifier {
+            rules,
+            debounce: Duration::from_secs(alerts_config.debounce_secs),
+            fahrenheit,
+            sender,
+        }
+    }
+
+    pub fn check(&mut self, now: Instant, stats: &SystemStats) {
+        for i in 0..self.rules.len() {
+            let rule = &mut self.rules[i];
+            let value = match rule.metric {
+                Metric::Temp => stats.cpu_temp as f64,
+                Metric::Ram => stats.ram_usage,
+                Metric::Disk => stats.disk_usage,
+            };
+            if (value > rule.threshold) == rule.active {
+                rule.pending_since = None;
+                continue;
+            }
+            let since = *rule.pending_since.get_or_insert(now);
+            if now.duration_since(since) < self.debounce {
+                continue;
+            }
+            rule.active = !rule.active;
+            rule.pending_since = None;
+            let (metric, threshold, active) = (rule.metric, rule.threshold, rule.active);
+            self.send(metric, value, threshold, active, &stats.hostname);
+        }
+    }
+
+    fn send(&self, metric: Metric, value: f64, threshold: f64, active: bool, hostname: &str) {
+        let (name, label, shown_value, shown_threshold, unit) = match metric {
+            Metric::Temp if self.fahrenheit => (
+                "temp",
+                "CPU temperature",
+                value * 9.0 / 5.0 + 32.0,
+                threshold * 9.0 / 5.0 + 32.0,
+                "°F",
+            ),
+            Metric::Temp => ("temp", "CPU temperature", value, threshold, "°C"),
+            Metric::Ram => ("ram", "RAM usage", value, threshold, "%"),
+            Metric::Disk => ("disk", "Disk usage", value, threshold, "%"),
+        };
+        let text = if active {
+            format!(
+                "{}: {} is {:.1}{}, above {:.1}{}",
+                hostname, label, shown_value, unit, shown_threshold, unit
+            )
+        } else {
+            format!(
+                "{}: {} is back to {:.1}{} (threshold {:.1}{})",
+                hostname, label, shown_value, unit, shown_threshold, unit
+            )
+        };
+        info!("Alert: {}", text);
+        // `content` is what Discord shows, `text` what Slack shows.
+        let payload = serde_json::json!({
+            "content": text,
+            "text": text,
+            "hostname": hostname,
+            "alert": name,
+            "state": if active { "triggered" } else { "cleared" },
+            "value": shown_value,
+            "threshold": shown_threshold,
+        });
+        if self.sender.send(payload.to_string()).is_err() {
+            warn!("Alert thread is not running");
+        }
+    }
+}
+
+fn run(url: &str, receiver: Receiver<String>) {
+    for payload in receiver {
+        let result = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail"])
+            .args(["--max-time", REQUEST_TIMEOUT_SECS])
+            .args(["--header", "Content-Type: application/json"])
+            .args(["--data", &payload])
+            .arg(url)
+            .output();
+        match result {
+            Ok(output) if output.status.success() => debug!("Alert sent"),
+            Ok(output) => warn!(
+                "Failed to send alert: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => warn!("Failed to run curl for the alert webhook: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    const DEBOUNCE: Duration = Duration::from_secs(30);
+
+    /// Notifier alerting above 70°C, with the receiver in place of the
+    /// webhook thread.
+    fn notifier(debounce: Duration, fahrenheit: bool) -> (AlertNotifier, Receiver<String>) {
+        let (sender, receiver) = mpsc::channel();
+        let notifier = AlertNotifier {
+            rules: vec![Rule {
+                metric: Metric::Temp,
+                threshold: 70.0,
+                active: false,
+                pending_since: None,
+            }],
+            debounce,
+            fahrenheit,
+            sender,
+        };
+        (notifier, receiver)
+    }
+
+    fn check(notifier: &mut AlertNotifier, now: Instant, cpu_temp: f32) {
+        let stats = SystemStats {
+            hostname: "pi".to_string(),
+            cpu_temp,
+            ..Default::default()
+        };
+        notifier.check(now, &stats);
+    }
+
+    fn sent(receiver: &Receiver<String>) -> Vec<Value> {
+        receiver
+            .try_iter()
+            .map(|payload| serde_json::from_str(&payload).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hovering_around_the_threshold_sends_nothing() {
+        let (mut notifier, receiver) = notifier(DEBOUNCE, false);
+        let start = Instant::now();
+        for step in 0..20 {
+            let temp = if step % 2 == 0 { 71.0 } else { 69.0 };
+            check(&mut notifier, start + Duration::from_secs(step * 10), temp);
+        }
+        assert!(sent(&receiver).is_empty());
+    }
+
+    #[test]
+    fn crossing_alerts_once_it_lasts_the_debounce() {
+        let (mut notifier, receiver) = notifier(DEBOUNCE, false);
+        let start = Instant::now();
+        check(&mut notifier, start, 72.0);
+        check(
+            &mut notifier,
+            start + DEBOUNCE - Duration::from_secs(1),
+            72.0,
+        );
+        assert!(sent(&receiver).is_empty());
+
+        check(&mut notifier, start + DEBOUNCE, 72.5);
+        let alerts = sent(&receiver);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0]["state"], "triggered");
+        assert_eq!(alerts[0]["alert"], "temp");
+        assert_eq!(alerts[0]["value"], 72.5);
+        assert_eq!(
+            alerts[0]["text"],
+            "pi: CPU temperature is 72.5°C, above 70.0°C"
+        );
+
+        // Staying above sends no more alerts.
+        check(&mut notifier, start + DEBOUNCE * 3, 75.0);
+        assert!(sent(&receiver).is_empty());
+    }
+
+    #[test]
+    fn clearing_also_waits_for_the_debounce_and_can_retrigger() {
+        let (mut notifier, receiver) = notifier(Duration::ZERO, false);
+        let start = Instant::now();
+        check(&mut notifier, start, 72.0);
+        assert_eq!(sent(&receiver)[0]["state"], "triggered");
+
+        notifier.debounce = DEBOUNCE;
+        let cooled = start + Duration::from_secs(10);
+        check(&mut notifier, cooled, 65.0);
+        // A spike back above restarts the wait for the clearing.
+        check(&mut notifier, cooled + Duration::from_secs(10), 71.0);
+        check(&mut notifier, cooled + Duration::from_secs(20), 65.0);
+        check(&mut notifier, cooled + DEBOUNCE, 65.0);
+        assert!(sent(&receiver).is_empty());
+
+        check(
+            &mut notifier,
+            cooled + Duration::from_secs(20) + DEBOUNCE,
+            64.0,
+        );
+        let alerts = sent(&receiver);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0]["state"], "cleared");
+        assert_eq!(alerts[0]["value"], 64.0);
+
+        let hot_again = cooled + DEBOUNCE * 3;
+        check(&mut notifier, hot_again, 80.0);
+        check(&mut notifier, hot_again + DEBOUNCE, 80.0);
+        assert_eq!(sent(&receiver)[0]["state"], "triggered");
+    }
+
+    #[test]
+    fn temperatures_are_shown_in_fahrenheit() {
+        let (mut notifier, receiver) = notifier(Duration::ZERO, true);
+        check(&mut notifier, Instant::now(), 80.0);
+        let alerts = sent(&receiver);
+        assert_eq!(alerts[0]["value"], 176.0);
+        assert_eq!(alerts[0]["threshold"], 158.0);
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::sync::OnceLock;
+
+const DEVICE_TREE_MODEL_PATH: &str = "/proc/device-tree/model";
+const CPUINFO_PATH: &str = "/proc/cpuinfo";
+
+/// Fan thresholds `(temp_on, temp_off)` in Celsius for boards that are not
+/// recognized.
+const GENERIC_FAN_THRESHOLDS: (f32, f32) = (60.0, 50.0);
+
+/// Board model, e.g. `Raspberry Pi 4 Model B Rev 1.4`. Read once; `None`
+/// when neither the device tree nor `/proc/cpuinfo` names it.
+pub fn model() -> Option<&'static str> {
+    static MODEL: OnceLock<Option<String>> = OnceLock::new();
+    MODEL.get_or_init(read_model).as_deref()
+}
+
+fn read_model() -> Option<String> {
+    // The device tree string is NUL-terminated.
+    if let Ok(contents) = fs::read_to_string(DEVICE_TREE_MODEL_PATH) {
+        let model = contents.trim_end_matches('\0').trim();
+        if !model.is_empty() {
+            return Some(model.to_string());
+        }
+    }
+
+    // Older kernels only have e.g. `Model : Raspberry Pi 3 Model B Rev 1.2`.
+    let cpuinfo = fs::read_to_string(CPUINFO_PATH).ok()?;
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "Model" && !value.trim().is_empty()).then(|| value.trim().to_string())
+    })
+}
+
+/// Whether the device tree or `/proc/cpuinfo` names a Raspberry Pi.
+pub fn is_raspberry_pi() -> bool {
+    model().is_some_and(|model| model.contains("Raspberry Pi"))
+}
+
+/// Default fan thresholds `(temp_on, temp_off)` in Celsius for the board.
+/// The Pi 3 and Zero 2 start soft-throttling at 60°C, the Pi 5 runs warmer
+/// and throttles later than the Pi 4.
+pub fn fan_thresholds() -> (f32, f32) {
+    let Some(model) = model() else {
+        return GENERIC_FAN_THRESHOLDS;
+    };
+    if model.contains("Raspberry Pi 5") {
+        (65.0, 55.0)
+    } else if model.contains("Raspberry Pi 3") || model.contains("Raspberry Pi Zero 2") {
+        (55.0, 45.0)
+    } else {
+        GENERIC_FAN_THRESHOLDS
+    }
+}
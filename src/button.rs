@@ -0,0 +1,66 @@
+use crate::config::ButtonConfig;
+use crate::fan_controller::gpio_error;
+use anyhow::Result;
+use linux_embedded_hal::gpio_cdev::{Chip, EventRequestFlags, LineRequestFlags};
+use log::{debug, info, warn};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const GPIO_CHIP_PATH: &str = "/dev/gpiochip0";
+
+/// Momentary push button on a GPIO line. Presses are counted on a dedicated
+/// thread and collected by the main loop with [`Button::take_presses`].
+pub struct Button {
+    presses: Arc<AtomicU32>,
+}
+
+impl Button {
+    pub fn new(pin: u32, button_config: &ButtonConfig) -> Result<Self> {
+        // The line idles high with a pull-up and is pulled low while pressed,
+        // unless `active_low` is off.
+        let edge = if button_config.active_low {
+            EventRequestFlags::FALLING_EDGE
+        } else {
+            EventRequestFlags::RISING_EDGE
+        };
+        let events = Chip::new(GPIO_CHIP_PATH)
+            .and_then(|mut chip| chip.get_line(pin))
+            .and_then(|line| line.events(LineRequestFlags::INPUT, edge, "rustberry-poe-monitor"))
+            .map_err(|e| gpio_error("button gpio_pin", pin, e))?;
+        info!("Button initialized on GPIO {}", pin);
+
+        let debounce = Duration::from_millis(button_config.debounce_ms).as_nanos() as u64;
+        let presses = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&presses);
+        thread::spawn(move || {
+            // Contact bounce shows up as a burst of edges; only the first
+            // edge after a quiet `debounce_ms` counts as a press.
+            let mut last_edge: Option<u64> = None;
+            for event in events {
+                match event {
+                    Ok(event) => {
+                        let timestamp = event.timestamp();
+                        if last_edge.is_none_or(|last| timestamp.saturating_sub(last) >= debounce) {
+                            debug!("Button pressed");
+                            counter.fetch_add(1, Ordering::Relaxed);
+                        }
+                        last_edge = Some(timestamp);
+                    }
+                    Err(e) => {
+                        warn!("Button event error, ignoring the button: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Button { presses })
+    }
+
+    /// Presses since the last call.
+    pub fn take_presses(&self) -> u32 {
+        self.presses.swap(0, Ordering::Relaxed)
+    }
+}
@@ -1,22 +1,94 @@
+use crate::board;
+use crate::network::IpRange;
+use chrono::format::{Item, StrftimeItems};
+use embedded_graphics::prelude::Point;
 use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+/// Largest pixel-shift offset that keeps the layouts on the panel.
+const MAX_PIXEL_SHIFT: i32 = 3;
+
+/// Longest fan kickstart.
+const MAX_KICKSTART_MS: u64 = 5000;
+
+/// Shortest main loop interval; faster refreshes would just burn CPU.
+const MIN_REFRESH_INTERVAL_MS: u64 = 100;
+
+/// Prefix of the environment variables that override config values.
+const ENV_PREFIX: &str = "RUSTBERRY_";
+
+/// Written when the config file does not exist yet, and used as is when it
+/// cannot be written.
+const DEFAULT_CONFIG: &str = include_str!("../config.toml");
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     pub display: DisplayConfig,
     pub fan: FanConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub disk: DiskConfig,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub power: PowerConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub control: ControlConfig,
+    #[serde(default)]
+    pub status_socket: StatusSocketConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub temperature: TemperatureConfig,
+    #[serde(default)]
+    pub button: ButtonConfig,
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_displays: Vec<ExtraDisplayConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DisplayConfig {
     #[serde(default = "default_brightness")]
     pub brightness: u8,
+    /// Contrast register (0-255) used instead of the one of the brightness
+    /// level; unset keeps the level's own contrast.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contrast: Option<u8>,
     #[serde(default = "default_screen_timeout")]
     pub screen_timeout: u64,
+    /// Seconds the dimming after `screen_timeout` is spread over; 0 dims
+    /// at once.
+    #[serde(default = "default_dim_fade_secs")]
+    pub dim_fade_secs: u64,
+    /// CPU temperature change that counts as activity for the screen
+    /// timeout; 0 ignores the temperature.
+    #[serde(default)]
+    pub activity_temp_delta: f32,
+    /// Never dim or switch off the display: overrides `screen_timeout`,
+    /// `enable_periodic_off` and `[schedule]`.
+    #[serde(default)]
+    pub always_on: bool,
+    /// Static frames only: no pixel shift, periodic off, scrolling or
+    /// blinking; dimming is left as the burn-in protection.
+    #[serde(default)]
+    pub reduced_motion: bool,
     #[serde(default = "default_periodic_off")]
     pub enable_periodic_off: bool,
     #[serde(default = "default_periodic_on_duration_seconds")]
@@ -25,12 +97,420 @@ pub struct DisplayConfig {
     pub periodic_off_duration: u64,
     #[serde(default = "default_refresh_interval_ms")]
     pub refresh_interval_ms: u64,
+    #[serde(default = "default_rotation")]
+    pub rotation: u16,
+    /// Arrangement of the pages: "landscape", "portrait" or "auto", which
+    /// follows the rotation.
+    #[serde(default = "default_layout")]
+    pub layout: String,
+    /// Alignment on the landscape stats page: "left", "center" or "right".
+    #[serde(default = "default_center_align")]
+    pub ip_align: String,
+    #[serde(default = "default_center_align")]
+    pub hostname_align: String,
+    #[serde(default = "default_value_align")]
+    pub value_align: String,
+    /// Dark text on a lit background.
+    #[serde(default)]
+    pub invert_colors: bool,
+    #[serde(default = "default_display_size")]
+    pub size: String,
+    /// Unit for the displayed CPU temperature and the fan thresholds, "C" or
+    /// "F". Thresholds are converted to Celsius when the config is loaded.
+    #[serde(default = "default_temp_unit")]
+    pub temp_unit: String,
+    #[serde(default = "default_page_interval_secs")]
+    pub page_interval_secs: u64,
+    #[serde(default = "default_show_bars")]
+    pub show_bars: bool,
+    /// Fields of the stats page; the remaining lines move up to fill the
+    /// space of disabled ones.
+    #[serde(default = "default_show_field")]
+    pub show_ip: bool,
+    #[serde(default = "default_show_field")]
+    pub show_cpu: bool,
+    #[serde(default = "default_show_field")]
+    pub show_temp: bool,
+    #[serde(default = "default_show_field")]
+    pub show_ram: bool,
+    #[serde(default = "default_show_field")]
+    pub show_rpm: bool,
+    #[serde(default = "default_show_field")]
+    pub show_hostname: bool,
+    /// Current CPU clock on the system page.
+    #[serde(default)]
+    pub show_cpu_freq: bool,
+    /// Show the load average divided by the number of CPU cores.
+    #[serde(default = "default_normalize_load")]
+    pub normalize_load: bool,
+    #[serde(default = "default_scroll_speed")]
+    pub scroll_speed: u32,
+    /// Decimal places of the CPU, RAM, swap, disk and temperature values.
+    #[serde(default = "default_decimals")]
+    pub decimals: usize,
+    /// Refreshes the displayed CPU usage is averaged over; 1 shows the
+    /// latest reading.
+    #[serde(default = "default_cpu_average_samples")]
+    pub cpu_average_samples: usize,
+    /// RAM usage as "percent", or used/total in "binary" (GiB) or
+    /// "decimal" (GB) units.
+    #[serde(default = "default_mem_format")]
+    pub mem_format: String,
+    /// Text of the QR code page, e.g. "http://{ip}"; unset leaves the page
+    /// out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qr_template: Option<String>,
+    /// Values shown on one line instead of their own rows on the stats
+    /// page, in this order: "temp", "cpu", "ram" and "rpm". Empty keeps
+    /// the rows.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summary_fields: Vec<String>,
+    #[serde(default = "default_summary_separator")]
+    pub summary_separator: String,
+    /// Stats page value drawn in a bold font: "temp", "cpu", "ram" or
+    /// "rpm". Unset draws them all alike.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emphasize: Option<String>,
+    /// Show the thermal page instead of the cycled pages while the fan runs.
+    #[serde(default)]
+    pub cooldown_page: bool,
+    /// Adds a page with the local time to the page cycle.
+    #[serde(default)]
+    pub show_clock: bool,
+    /// strftime-style format of the time, e.g. "%H:%M:%S".
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+    /// Temperature samples in the sparkline on 128x64 panels; 0 shows the
+    /// hostname there instead.
+    #[serde(default)]
+    pub sparkline_samples: usize,
+    #[serde(default = "default_sparkline_interval_secs")]
+    pub sparkline_interval_secs: u64,
+    /// Font of the values on the landscape pages: "pcsenior", "6x10" or
+    /// "9x15" (128x64 panels only).
+    #[serde(default = "default_font")]
+    pub font: String,
+    /// Burn-in protection: `[x, y]` offsets cycled every `shift_interval_secs`.
+    #[serde(default = "default_shift_pattern")]
+    pub shift_pattern: Vec<(i32, i32)>,
+    #[serde(default = "default_shift_interval_secs")]
+    pub shift_interval_secs: u64,
+    /// "pattern" steps through `shift_pattern`, "random" jumps to a random
+    /// offset within `shift_range` every `shift_interval_secs`.
+    #[serde(default = "default_shift_mode")]
+    pub shift_mode: String,
+    /// Largest `[x, y]` offset of the random shift, which moves right and
+    /// down only.
+    #[serde(default = "default_shift_range")]
+    pub shift_range: (i32, i32),
+    /// Fixed seed for the random shift, so runs repeat the same offsets.
+    /// Seeded from the clock when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shift_seed: Option<u64>,
+    /// Seconds the startup splash is shown; 0 skips it.
+    #[serde(default = "default_splash_duration_secs")]
+    pub splash_duration_secs: u64,
+    /// Tries to initialize the panel at startup before running without it.
+    #[serde(default = "default_init_attempts")]
+    pub init_attempts: u32,
+    /// Re-initializes the panels every N seconds while they are on, in case
+    /// a controller stopped taking updates; 0 disables it.
+    #[serde(default)]
+    pub reinit_interval_secs: u64,
+    #[serde(default = "default_i2c_bus")]
+    pub i2c_bus: String,
+    #[serde(default = "default_i2c_address")]
+    pub i2c_address: u8,
+    /// Page this display always shows; unset follows `page_interval_secs`.
+    #[serde(default)]
+    pub page: Option<String>,
+}
+
+/// An additional panel on the same bus. Everything not set here is taken
+/// from `[display]`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExtraDisplayConfig {
+    pub i2c_address: u8,
+    #[serde(default)]
+    pub page: Option<String>,
+    #[serde(default)]
+    pub size: Option<String>,
+    #[serde(default)]
+    pub rotation: Option<u16>,
+    #[serde(default)]
+    pub layout: Option<String>,
+    #[serde(default)]
+    pub font: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FanConfig {
-    pub temp_on: f32,
-    pub temp_off: f32,
+    /// Unset picks the default for the detected board when loading; see
+    /// [`FanConfig::temp_on`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_on: Option<f32>,
+    /// Either `temp_off` or `hysteresis` is set; see [`FanConfig::temp_off`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_off: Option<f32>,
+    /// Degrees below `temp_on` at which the fan turns off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hysteresis: Option<f32>,
+    /// PCF8574 expander pin (P0-P7) driving the fan.
+    #[serde(default = "default_fan_gpio_pin")]
+    pub gpio_pin: u8,
+    /// Drive the pin high instead of low to switch the fan on.
+    #[serde(default = "default_fan_invert")]
+    pub invert: bool,
+    #[serde(default)]
+    pub curve: Vec<(f32, u8)>,
+    /// Drive the fan from the Pi's hardware PWM channel instead of the
+    /// expander pin.
+    #[serde(default)]
+    pub pwm_hardware: bool,
+    #[serde(default)]
+    pub pwm_channel: u8,
+    #[serde(default = "default_pwm_freq_hz")]
+    pub pwm_freq_hz: u32,
+    #[serde(default)]
+    pub tach_pin: Option<u32>,
+    #[serde(default = "default_min_run_secs")]
+    pub min_run_secs: u64,
+    /// Milliseconds at full duty when the fan curve starts the fan from rest
+    /// at a lower duty cycle; 0 disables.
+    #[serde(default)]
+    pub kickstart_ms: u64,
+    /// Fan state at startup: "off", "on" or "auto", which checks the CPU
+    /// temperature against the thresholds right away.
+    #[serde(default = "default_boot_behavior")]
+    pub boot_behavior: String,
+    /// Seconds `SIGUSR1` runs the fan at full speed before automatic
+    /// control resumes.
+    #[serde(default = "default_max_cool_secs")]
+    pub max_cool_secs: u64,
+    /// Weight (0.0-1.0) of the previous temperature in the moving average;
+    /// 0.0 disables smoothing.
+    #[serde(default = "default_temp_smoothing")]
+    pub temp_smoothing: f32,
+    /// CPU temperature at which the Pi is shut down. Unset disables the
+    /// emergency shutdown.
+    #[serde(default)]
+    pub temp_critical: Option<f32>,
+    #[serde(default = "default_critical_readings")]
+    pub critical_readings: u32,
+    #[serde(default = "default_critical_command")]
+    pub critical_command: String,
+    /// Shell commands run in the background when the fan turns on or off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_fan_on: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_fan_off: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    #[serde(default)]
+    pub net_interface: Option<String>,
+    /// Only take the displayed IP address from this interface, so VPN or
+    /// docker interfaces are not picked by accident.
+    #[serde(default)]
+    pub ip_interface: Option<String>,
+    /// Address family of the displayed IP: "v4", "v6" or "auto".
+    #[serde(default = "default_ip_version")]
+    pub ip_version: String,
+    /// Address ranges, e.g. docker bridges, whose addresses are only
+    /// displayed when the host has no other address.
+    #[serde(default = "default_ip_skip_ranges")]
+    pub ip_skip_ranges: Vec<String>,
+    /// Seconds the displays blink after the IP address changed; 0 disables.
+    #[serde(default)]
+    pub ip_change_flash_secs: u64,
+    /// Seconds to wait at startup for an IP address before entering the
+    /// main loop, e.g. while DHCP is still running after boot; 0 disables.
+    #[serde(default)]
+    pub startup_delay_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DiskConfig {
+    #[serde(default = "default_disk_mount_point")]
+    pub mount_point: String,
+    #[serde(default = "default_disk_warn_threshold")]
+    pub warn_threshold: f64,
+}
+
+/// INA219 power sensor, e.g. on PoE HATs with power metering.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PowerConfig {
+    /// Sensor address; unset disables the power page.
+    #[serde(default)]
+    pub i2c_address: Option<u8>,
+    #[serde(default = "default_i2c_bus")]
+    pub i2c_bus: String,
+    /// Resistance of the shunt the current is measured across.
+    #[serde(default = "default_shunt_ohms")]
+    pub shunt_ohms: f32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MemoryConfig {
+    /// Swap usage (percent) from which the value is highlighted.
+    #[serde(default = "default_swap_warn_threshold")]
+    pub swap_warn_threshold: f64,
+}
+
+/// Local times ("HH:MM") between which the display is switched off, e.g.
+/// overnight. The window may wrap around midnight.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ScheduleConfig {
+    #[serde(default)]
+    pub off_start: Option<String>,
+    #[serde(default)]
+    pub off_end: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ButtonConfig {
+    /// GPIO line of a push button that wakes the display and shows the next
+    /// page. Unset disables the button.
+    #[serde(default)]
+    pub gpio_pin: Option<u32>,
+    /// The button pulls the line low when pressed.
+    #[serde(default = "default_button_active_low")]
+    pub active_low: bool,
+    #[serde(default = "default_button_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HeartbeatConfig {
+    /// GPIO line of an LED toggled on every refresh. Unset disables it.
+    #[serde(default)]
+    pub gpio_pin: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TemperatureConfig {
+    /// Where the CPU temperature comes from: "sysfs" (the sensor files),
+    /// "vcgencmd" or "command".
+    #[serde(default = "default_temp_source")]
+    pub temp_source: String,
+    /// Shell command printing the temperature in degrees Celsius, for
+    /// `temp_source = "command"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_command: Option<String>,
+    #[serde(default = "default_temp_sensor_path")]
+    pub temp_sensor_path: String,
+    /// Further sensors read alongside `temp_sensor_path`.
+    #[serde(default)]
+    pub extra_sensor_paths: Vec<String>,
+    /// How several readings are combined: "max" or "average".
+    #[serde(default = "default_temp_combine")]
+    pub combine: String,
+    /// Sensor values are divided by this to get degrees Celsius.
+    #[serde(default = "default_temp_divisor")]
+    pub temp_divisor: f32,
+    /// Read the GPU temperature with `vcgencmd measure_temp`.
+    #[serde(default)]
+    pub gpu: bool,
+    /// Drive the fan from the higher of the CPU and GPU temperature.
+    #[serde(default)]
+    pub fan_include_gpu: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// File the samples are appended to; unset disables stats logging.
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default = "default_logging_interval_secs")]
+    pub interval_secs: u64,
+    /// "csv" or "jsonl".
+    #[serde(default = "default_logging_format")]
+    pub format: String,
+    #[serde(default = "default_logging_max_size_kb")]
+    pub max_size_kb: u64,
+}
+
+/// File the log messages are written to besides the console.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LogConfig {
+    /// Unset keeps the console as the only output.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Most verbose level written to the file: "error", "warn", "info",
+    /// "debug" or "trace". The console follows `RUST_LOG`.
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    #[serde(default = "default_log_max_size_kb")]
+    pub max_size_kb: u64,
+}
+
+/// Webhook notifications when a stat crosses a threshold.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AlertsConfig {
+    /// URL the alerts are POSTed to, e.g. a Discord or Slack webhook;
+    /// unset disables alerts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// CPU temperature in the `temp_unit` of `[display]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_above: Option<f32>,
+    /// RAM usage in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ram_above: Option<f64>,
+    /// Usage of `disk.mount_point` in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_above: Option<f64>,
+    /// Seconds a value has to stay above, or back below, its threshold
+    /// before an alert is sent.
+    #[serde(default = "default_alerts_debounce_secs")]
+    pub debounce_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MetricsConfig {
+    #[serde(default = "default_metrics_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_metrics_port")]
+    pub port: u16,
+}
+
+/// HTTP server for switching the display and fan by hand.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ControlConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_control_port")]
+    pub port: u16,
+    /// Required as `Authorization: Bearer <token>` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Seconds a manual override lasts before the automatic control resumes.
+    #[serde(default = "default_override_secs")]
+    pub override_secs: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct StatusSocketConfig {
+    /// Unix domain socket serving the latest stats as JSON; unset disables it.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MqttConfig {
+    #[serde(default = "default_mqtt_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_mqtt_host")]
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default = "default_mqtt_topic_prefix")]
+    pub topic_prefix: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
 }
 
 fn default_brightness() -> u8 {
@@ -51,30 +531,343 @@ fn default_periodic_off_duration_seconds() -> u64 {
 fn default_refresh_interval_ms() -> u64 {
     1000
 }
+fn default_rotation() -> u16 {
+    0
+}
+fn default_layout() -> String {
+    "auto".to_string()
+}
+fn default_center_align() -> String {
+    "center".to_string()
+}
+fn default_value_align() -> String {
+    "right".to_string()
+}
+fn default_display_size() -> String {
+    "128x32".to_string()
+}
+fn default_temp_unit() -> String {
+    "C".to_string()
+}
+fn default_show_bars() -> bool {
+    false
+}
+fn default_show_field() -> bool {
+    true
+}
+fn default_normalize_load() -> bool {
+    false
+}
+fn default_scroll_speed() -> u32 {
+    4
+}
+fn default_cpu_average_samples() -> usize {
+    1
+}
+fn default_decimals() -> usize {
+    1
+}
+fn default_mem_format() -> String {
+    "percent".to_string()
+}
+fn default_summary_separator() -> String {
+    " ".to_string()
+}
+fn default_time_format() -> String {
+    "%H:%M".to_string()
+}
+fn default_i2c_bus() -> String {
+    "/dev/i2c-1".to_string()
+}
+fn default_i2c_address() -> u8 {
+    0x3C
+}
+fn default_page_interval_secs() -> u64 {
+    0
+}
+fn default_sparkline_interval_secs() -> u64 {
+    5
+}
+fn default_font() -> String {
+    "pcsenior".to_string()
+}
+fn default_dim_fade_secs() -> u64 {
+    0
+}
+fn default_shift_pattern() -> Vec<(i32, i32)> {
+    vec![(0, 0), (1, 0)]
+}
+fn default_shift_mode() -> String {
+    "pattern".to_string()
+}
+fn default_shift_range() -> (i32, i32) {
+    (MAX_PIXEL_SHIFT, MAX_PIXEL_SHIFT)
+}
+fn default_shift_interval_secs() -> u64 {
+    60
+}
+fn default_init_attempts() -> u32 {
+    5
+}
+fn default_splash_duration_secs() -> u64 {
+    2
+}
+fn default_fan_gpio_pin() -> u8 {
+    0
+}
+fn default_fan_invert() -> bool {
+    false
+}
+fn default_critical_readings() -> u32 {
+    5
+}
+fn default_critical_command() -> String {
+    "shutdown -h now".to_string()
+}
+fn default_pwm_freq_hz() -> u32 {
+    25_000
+}
+fn default_min_run_secs() -> u64 {
+    0
+}
+fn default_max_cool_secs() -> u64 {
+    600
+}
+fn default_boot_behavior() -> String {
+    "off".to_string()
+}
+fn default_temp_smoothing() -> f32 {
+    0.0
+}
+fn default_ip_version() -> String {
+    "auto".to_string()
+}
+fn default_ip_skip_ranges() -> Vec<String> {
+    vec!["172.16.0.0/12".to_string()]
+}
+fn default_disk_mount_point() -> String {
+    "/".to_string()
+}
+fn default_disk_warn_threshold() -> f64 {
+    90.0
+}
+fn default_shunt_ohms() -> f32 {
+    0.1
+}
+fn default_swap_warn_threshold() -> f64 {
+    50.0
+}
+fn default_button_active_low() -> bool {
+    true
+}
+fn default_button_debounce_ms() -> u64 {
+    50
+}
+fn default_temp_source() -> String {
+    "sysfs".to_string()
+}
+fn default_temp_sensor_path() -> String {
+    "/sys/class/thermal/thermal_zone0/temp".to_string()
+}
+fn default_temp_combine() -> String {
+    "max".to_string()
+}
+fn default_temp_divisor() -> f32 {
+    1000.0
+}
+fn default_logging_interval_secs() -> u64 {
+    60
+}
+fn default_logging_format() -> String {
+    "csv".to_string()
+}
+fn default_logging_max_size_kb() -> u64 {
+    10 * 1024
+}
+fn default_alerts_debounce_secs() -> u64 {
+    30
+}
+fn default_log_level() -> String {
+    "info".to_string()
+}
+fn default_log_max_size_kb() -> u64 {
+    1024
+}
+fn default_metrics_enabled() -> bool {
+    false
+}
+fn default_metrics_port() -> u16 {
+    9101
+}
+fn default_control_port() -> u16 {
+    9102
+}
+fn default_override_secs() -> u64 {
+    3600
+}
+fn default_mqtt_enabled() -> bool {
+    false
+}
+fn default_mqtt_host() -> String {
+    "localhost".to_string()
+}
+fn default_mqtt_port() -> u16 {
+    1883
+}
+fn default_mqtt_topic_prefix() -> String {
+    "rustberry-poe-monitor".to_string()
+}
+
+/// Writes the commented example config, which has every setting at its
+/// default, to `config_path`.
+fn write_default_config(config_path: &Path) -> std::io::Result<()> {
+    if let Some(parent_dir) = config_path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    fs::write(config_path, DEFAULT_CONFIG)
+}
+
+/// Environment variables of the process that are valid UTF-8, the input of
+/// `apply_env_overrides`.
+fn env_vars() -> impl Iterator<Item = (String, String)> {
+    env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+}
+
+/// Sets `[section] key` for every `RUSTBERRY_<SECTION>_<KEY>` variable in
+/// `vars`, e.g. `RUSTBERRY_FAN_TEMP_ON=60`. Variables that name no section
+/// are left alone.
+fn apply_env_overrides(
+    table: &mut toml::Table,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let defaults = toml::Table::try_from(Config::default())?;
+    // Longest first, so `status_socket` is not taken for a `status` section.
+    let mut sections: Vec<&str> = defaults
+        .iter()
+        .filter(|(_, value)| value.is_table())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    sections.sort_by_key(|section| std::cmp::Reverse(section.len()));
+
+    for (name, raw) in vars {
+        let Some(rest) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let rest = rest.to_ascii_lowercase();
+        let Some((section, key)) = sections.iter().find_map(|&section| {
+            let key = rest.strip_prefix(section)?.strip_prefix('_')?;
+            (!key.is_empty()).then_some((section, key))
+        }) else {
+            continue;
+        };
+        let current = table
+            .get(section)
+            .and_then(|values| values.get(key))
+            .or_else(|| defaults[section].get(key));
+        let value = parse_env_value(&raw, current);
+        let values = table
+            .entry(section)
+            .or_insert_with(|| toml::Table::new().into())
+            .as_table_mut()
+            .ok_or_else(|| format!("{} is not a section", section))?;
+        info!("Using {}.{} from {}", section, key, name);
+        values.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Reads `raw` as a TOML value, e.g. `60`, `true` or `[[0, 0], [1, 0]]`.
+/// Anything else is taken as a string, as is a number for a setting that
+/// is a string (e.g. a numeric password).
+fn parse_env_value(raw: &str, current: Option<&toml::Value>) -> toml::Value {
+    let parsed = toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"));
+    match parsed {
+        Some(value) if value.is_str() || !current.is_some_and(toml::Value::is_str) => value,
+        _ => toml::Value::String(raw.to_string()),
+    }
+}
 
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_path = Self::get_config_path()?;
+    /// Loads the config from `config_path`, writing the defaults there first
+    /// if the file does not exist yet. `RUSTBERRY_<SECTION>_<KEY>`
+    /// environment variables take precedence over the file.
+    pub fn load_from(config_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load(config_path, true)
+    }
 
-        if let Some(parent_dir) = config_path.parent() {
-            fs::create_dir_all(parent_dir)?;
-        }
+    /// Like `load_from`, but a missing file is not created, e.g. for
+    /// `--print-config`, which should not leave files behind.
+    pub fn read_from(config_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load(config_path, false)
+    }
+
+    fn load(config_path: &Path, create_missing: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_with_env(config_path, create_missing, env_vars())
+    }
 
-        if !config_path.exists() {
-            let default_config = Config::default();
-            let toml_string = toml::to_string_pretty(&default_config)?;
-            fs::write(&config_path, toml_string)?;
-            info!("Created default config file at: {:?}", config_path);
-            Ok(default_config)
+    /// `load` with `vars` in place of the process environment.
+    fn load_with_env(
+        config_path: &Path,
+        create_missing: bool,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_str = if !config_path.exists() {
+            info!(
+                "No config file at {:?}, using the built-in defaults",
+                config_path
+            );
+            if create_missing {
+                match write_default_config(config_path) {
+                    Ok(()) => info!("Created default config file at: {:?}", config_path),
+                    Err(e) => warn!(
+                        "Could not create a default config file at {:?}: {}",
+                        config_path, e
+                    ),
+                }
+            }
+            DEFAULT_CONFIG.to_string()
         } else {
             debug!("Loading config file from: {:?}", config_path);
-            let config_str = fs::read_to_string(config_path)?;
-            let config: Config = toml::from_str(&config_str)?;
-            Ok(config)
+            fs::read_to_string(config_path)
+                .map_err(|e| format!("Failed to read config file {:?}: {}", config_path, e))?
+        };
+        let mut table: toml::Table = toml::from_str(&config_str)?;
+        apply_env_overrides(&mut table, vars)?;
+        let mut config: Config = table.try_into()?;
+        config
+            .fan
+            .apply_board_defaults(config.display.use_fahrenheit());
+        if config.display.use_fahrenheit() {
+            config.fan.thresholds_to_celsius();
+            config.alerts.temp_above = config.alerts.temp_above.map(fahrenheit_to_celsius);
+        }
+        config.validate()?;
+        if config.display.refresh_interval_ms < MIN_REFRESH_INTERVAL_MS {
+            warn!(
+                "display.refresh_interval_ms {} is below the minimum, using {} ms",
+                config.display.refresh_interval_ms, MIN_REFRESH_INTERVAL_MS
+            );
         }
+        Ok(config)
     }
 
-    fn get_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    /// The settings in effect as TOML, for `--print-config`: the file with
+    /// the environment overrides and board defaults applied, thresholds in
+    /// `display.temp_unit` as in the file.
+    pub fn into_toml(mut self) -> Result<String, toml::ser::Error> {
+        if self.display.use_fahrenheit() {
+            self.fan.thresholds_to_fahrenheit();
+            self.alerts.temp_above = self.alerts.temp_above.map(celsius_to_fahrenheit);
+        }
+        toml::to_string_pretty(&self)
+    }
+
+    /// `$HOME/.config/rustberry-poe-monitor/config.toml`, or `config.toml`
+    /// in the working directory when `HOME` is not set.
+    pub fn default_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
         match env::var("HOME") {
             Ok(home_dir) => {
                 let mut path = PathBuf::from(home_dir);
@@ -90,6 +883,399 @@ impl Config {
         }
     }
 
+    /// Checks the invariants serde cannot express. Every problem is listed in
+    /// the returned error so a hand-edited file can be fixed in one go.
+    /// Temperatures are checked in Celsius, after `thresholds_to_celsius`.
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let display = &self.display;
+        let fan = &self.fan;
+        let mut problems = Vec::new();
+
+        if display.brightness > 4 {
+            problems.push(format!(
+                "display.brightness must be between 0 and 4, got {}",
+                display.brightness
+            ));
+        }
+        if display.activity_temp_delta < 0.0 {
+            problems.push(format!(
+                "display.activity_temp_delta must not be negative, got {}",
+                display.activity_temp_delta
+            ));
+        }
+        if display.enable_periodic_off
+            && (display.periodic_on_duration == 0 || display.periodic_off_duration == 0)
+        {
+            problems.push(
+                "display.periodic_on_duration and display.periodic_off_duration must be \
+                 greater than 0 when enable_periodic_off is set"
+                    .to_string(),
+            );
+        }
+        if display.decimals > 2 {
+            problems.push(format!(
+                "display.decimals must be 0, 1 or 2, got {}",
+                display.decimals
+            ));
+        }
+        if display.cpu_average_samples == 0 {
+            problems.push("display.cpu_average_samples must be greater than 0".to_string());
+        }
+        for (name, align) in [
+            ("ip_align", &display.ip_align),
+            ("hostname_align", &display.hostname_align),
+            ("value_align", &display.value_align),
+        ] {
+            if !["left", "center", "right"].contains(&align.as_str()) {
+                problems.push(format!(
+                    "display.{} must be left, center or right, got '{}'",
+                    name, align
+                ));
+            }
+        }
+        if !["percent", "binary", "decimal"].contains(&display.mem_format.as_str()) {
+            problems.push(format!(
+                "display.mem_format must be percent, binary or decimal, got '{}'",
+                display.mem_format
+            ));
+        }
+        for field in &display.summary_fields {
+            if !["temp", "cpu", "ram", "rpm"].contains(&field.as_str()) {
+                problems.push(format!(
+                    "display.summary_fields entries must be temp, cpu, ram or rpm, got '{}'",
+                    field
+                ));
+            }
+        }
+        if let Some(field) = &display.emphasize {
+            if !["temp", "cpu", "ram", "rpm"].contains(&field.as_str()) {
+                problems.push(format!(
+                    "display.emphasize must be temp, cpu, ram or rpm, got '{}'",
+                    field
+                ));
+            }
+        }
+        if StrftimeItems::new(&display.time_format).any(|item| item == Item::Error) {
+            problems.push(format!(
+                "display.time_format is not a valid strftime format: '{}'",
+                display.time_format
+            ));
+        }
+        if display.init_attempts == 0 {
+            problems.push("display.init_attempts must be greater than 0".to_string());
+        }
+        if display.sparkline_samples == 1 {
+            problems.push("display.sparkline_samples must be 0 or at least 2".to_string());
+        }
+        if display.shift_pattern.is_empty() {
+            problems.push("display.shift_pattern must contain at least one offset".to_string());
+        }
+        if display
+            .shift_pattern
+            .iter()
+            .any(|&(x, y)| x.abs() > MAX_PIXEL_SHIFT || y.abs() > MAX_PIXEL_SHIFT)
+        {
+            problems.push(format!(
+                "display.shift_pattern offsets must be between -{0} and {0} pixels",
+                MAX_PIXEL_SHIFT
+            ));
+        }
+        if !["pattern", "random"].contains(&display.shift_mode.as_str()) {
+            problems.push(format!(
+                "display.shift_mode must be pattern or random, got '{}'",
+                display.shift_mode
+            ));
+        }
+        let (range_x, range_y) = display.shift_range;
+        if !(0..=MAX_PIXEL_SHIFT).contains(&range_x) || !(0..=MAX_PIXEL_SHIFT).contains(&range_y) {
+            problems.push(format!(
+                "display.shift_range values must be between 0 and {} pixels",
+                MAX_PIXEL_SHIFT
+            ));
+        }
+        let display_configs = self.display_configs();
+        for (i, panel) in display_configs.iter().enumerate() {
+            let name = match i {
+                0 => "display".to_string(),
+                i => format!("extra_displays[{}]", i - 1),
+            };
+            if !["128x32", "128x64"].contains(&panel.size.as_str()) {
+                problems.push(format!(
+                    "{}.size must be 128x32 or 128x64, got '{}'",
+                    name, panel.size
+                ));
+            }
+            if !["pcsenior", "6x10", "9x15"].contains(&panel.font.as_str()) {
+                problems.push(format!(
+                    "{}.font must be pcsenior, 6x10 or 9x15, got '{}'",
+                    name, panel.font
+                ));
+            } else if panel.font == "9x15" && panel.size != "128x64" {
+                problems.push(format!("{}.font 9x15 needs a 128x64 panel", name));
+            }
+            if ![0, 90, 180, 270].contains(&panel.rotation) {
+                problems.push(format!(
+                    "{}.rotation must be 0, 90, 180 or 270, got {}",
+                    name, panel.rotation
+                ));
+            }
+            if !["auto", "landscape", "portrait"].contains(&panel.layout.as_str()) {
+                problems.push(format!(
+                    "{}.layout must be auto, landscape or portrait, got '{}'",
+                    name, panel.layout
+                ));
+            }
+            if let Some(page) = &panel.page {
+                if ![
+                    "stats", "system", "disk", "network", "cores", "power", "qr", "clock",
+                    "cooldown",
+                ]
+                .contains(&page.as_str())
+                {
+                    problems.push(format!(
+                        "{}.page must be stats, system, disk, network, cores, power, qr, clock or cooldown, got '{}'",
+                        name, page
+                    ));
+                }
+            }
+            if display_configs[..i]
+                .iter()
+                .any(|other| other.i2c_address == panel.i2c_address)
+            {
+                problems.push(format!(
+                    "{}.i2c_address 0x{:02X} is already used by another display",
+                    name, panel.i2c_address
+                ));
+            }
+        }
+        if !display.temp_unit.eq_ignore_ascii_case("C") && !display.use_fahrenheit() {
+            problems.push(format!(
+                "display.temp_unit must be C or F, got '{}'",
+                display.temp_unit
+            ));
+        }
+
+        match (fan.temp_off, fan.hysteresis) {
+            (Some(_), Some(_)) => {
+                problems.push("fan.temp_off and fan.hysteresis cannot both be set".to_string())
+            }
+            (None, None) => problems.push("fan.temp_off or fan.hysteresis must be set".to_string()),
+            (None, Some(hysteresis)) if hysteresis <= 0.0 => problems.push(format!(
+                "fan.hysteresis must be greater than 0, got {}",
+                hysteresis
+            )),
+            _ => {}
+        }
+        if fan.temp_on() <= 0.0 || fan.temp_off() <= 0.0 {
+            problems.push("fan.temp_on and fan.temp_off must be above 0°C".to_string());
+        }
+        if fan.temp_off() >= fan.temp_on() {
+            problems.push(format!(
+                "fan.temp_off ({:.1}°C) must be lower than fan.temp_on ({:.1}°C)",
+                fan.temp_off(),
+                fan.temp_on()
+            ));
+        }
+        if fan.gpio_pin > 7 {
+            problems.push(format!(
+                "fan.gpio_pin must be an expander pin between 0 and 7, got {}",
+                fan.gpio_pin
+            ));
+        }
+        if !(0.0..1.0).contains(&fan.temp_smoothing) {
+            problems.push(format!(
+                "fan.temp_smoothing must be at least 0.0 and below 1.0, got {}",
+                fan.temp_smoothing
+            ));
+        }
+        if fan.pwm_hardware && fan.pwm_channel > 1 {
+            problems.push(format!(
+                "fan.pwm_channel must be 0 or 1, got {}",
+                fan.pwm_channel
+            ));
+        }
+        if fan.pwm_hardware && fan.pwm_freq_hz == 0 {
+            problems.push("fan.pwm_freq_hz must be greater than 0".to_string());
+        }
+        if fan.kickstart_ms > MAX_KICKSTART_MS {
+            problems.push(format!(
+                "fan.kickstart_ms must be at most {}",
+                MAX_KICKSTART_MS
+            ));
+        }
+        if fan.curve.iter().any(|&(_, duty)| duty > 100) {
+            problems.push("fan.curve duty cycles must be between 0 and 100".to_string());
+        }
+        if let Some(temp_critical) = fan.temp_critical {
+            if temp_critical <= fan.temp_on() {
+                problems.push(format!(
+                    "fan.temp_critical ({:.1}°C) must be higher than fan.temp_on ({:.1}°C)",
+                    temp_critical,
+                    fan.temp_on()
+                ));
+            }
+            if fan.critical_readings == 0 {
+                problems.push("fan.critical_readings must be greater than 0".to_string());
+            }
+            if fan.critical_command.trim().is_empty() {
+                problems.push("fan.critical_command must not be empty".to_string());
+            }
+        }
+        if fan.curve.windows(2).any(|w| w[0].0 >= w[1].0) {
+            problems.push("fan.curve temperatures must be in strictly ascending order".to_string());
+        }
+        if !["off", "on", "auto"].contains(&fan.boot_behavior.as_str()) {
+            problems.push(format!(
+                "fan.boot_behavior must be off, on or auto, got '{}'",
+                fan.boot_behavior
+            ));
+        }
+
+        if !["v4", "v6", "auto"].contains(&self.network.ip_version.as_str()) {
+            problems.push(format!(
+                "network.ip_version must be v4, v6 or auto, got '{}'",
+                self.network.ip_version
+            ));
+        }
+        for range in &self.network.ip_skip_ranges {
+            if let Err(e) = range.parse::<IpRange>() {
+                problems.push(format!("network.ip_skip_ranges: {}", e));
+            }
+        }
+        if !(0.0..=100.0).contains(&self.disk.warn_threshold) {
+            problems.push(format!(
+                "disk.warn_threshold must be between 0 and 100, got {}",
+                self.disk.warn_threshold
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.memory.swap_warn_threshold) {
+            problems.push(format!(
+                "memory.swap_warn_threshold must be between 0 and 100, got {}",
+                self.memory.swap_warn_threshold
+            ));
+        }
+
+        match self.temperature.temp_source.as_str() {
+            "sysfs" | "vcgencmd" => {}
+            "command" if self.temperature.temp_command.is_none() => problems.push(
+                "temperature.temp_source = \"command\" requires temperature.temp_command"
+                    .to_string(),
+            ),
+            "command" => {}
+            source => problems.push(format!(
+                "temperature.temp_source must be sysfs, vcgencmd or command, got '{}'",
+                source
+            )),
+        }
+        if !["max", "average"].contains(&self.temperature.combine.as_str()) {
+            problems.push(format!(
+                "temperature.combine must be max or average, got '{}'",
+                self.temperature.combine
+            ));
+        }
+        if let Some(pin) = self.heartbeat.gpio_pin {
+            if self.button.gpio_pin == Some(pin) || self.fan.tach_pin == Some(pin) {
+                problems.push(format!(
+                    "heartbeat.gpio_pin {} is already used by the button or the fan tach_pin",
+                    pin
+                ));
+            }
+        }
+        if self.temperature.temp_divisor <= 0.0 {
+            problems.push(format!(
+                "temperature.temp_divisor must be greater than 0, got {}",
+                self.temperature.temp_divisor
+            ));
+        }
+        if self.control.enabled && self.control.override_secs == 0 {
+            problems.push("control.override_secs must be greater than 0".to_string());
+        }
+        if self.power.shunt_ohms <= 0.0 {
+            problems.push(format!(
+                "power.shunt_ohms must be greater than 0, got {}",
+                self.power.shunt_ohms
+            ));
+        }
+        if self.temperature.fan_include_gpu && !self.temperature.gpu {
+            problems.push("temperature.fan_include_gpu requires temperature.gpu".to_string());
+        }
+        let schedule = &self.schedule;
+        match (&schedule.off_start, &schedule.off_end) {
+            (None, None) => {}
+            (Some(start), Some(end)) => {
+                for (key, value) in [("off_start", start), ("off_end", end)] {
+                    if parse_time_of_day(value).is_none() {
+                        problems.push(format!(
+                            "schedule.{} must be a time as HH:MM, got '{}'",
+                            key, value
+                        ));
+                    }
+                }
+            }
+            _ => problems
+                .push("schedule.off_start and schedule.off_end must be set together".to_string()),
+        }
+        if self.logging.file.is_some() {
+            if !["csv", "jsonl"].contains(&self.logging.format.as_str()) {
+                problems.push(format!(
+                    "logging.format must be csv or jsonl, got '{}'",
+                    self.logging.format
+                ));
+            }
+            if self.logging.interval_secs == 0 {
+                problems.push("logging.interval_secs must be greater than 0".to_string());
+            }
+        }
+        if self.alerts.webhook_url.is_some()
+            && self.alerts.temp_above.is_none()
+            && self.alerts.ram_above.is_none()
+            && self.alerts.disk_above.is_none()
+        {
+            problems.push(
+                "alerts.webhook_url needs at least one of alerts.temp_above, ram_above or disk_above"
+                    .to_string(),
+            );
+        }
+        if !["error", "warn", "info", "debug", "trace"].contains(&self.log.level.as_str()) {
+            problems.push(format!(
+                "log.level must be error, warn, info, debug or trace, got '{}'",
+                self.log.level
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid config: {}", problems.join("; ")).into())
+        }
+    }
+
+    /// Settings for every configured panel: `[display]` first, followed by
+    /// the `[[extra_displays]]` with their overrides applied.
+    pub fn display_configs(&self) -> Vec<DisplayConfig> {
+        let extras = self.extra_displays.iter().map(|extra| DisplayConfig {
+            i2c_address: extra.i2c_address,
+            page: extra.page.clone(),
+            size: extra
+                .size
+                .clone()
+                .unwrap_or_else(|| self.display.size.clone()),
+            rotation: extra.rotation.unwrap_or(self.display.rotation),
+            layout: extra
+                .layout
+                .clone()
+                .unwrap_or_else(|| self.display.layout.clone()),
+            font: extra
+                .font
+                .clone()
+                .unwrap_or_else(|| self.display.font.clone()),
+            ..self.display.clone()
+        });
+        std::iter::once(self.display.clone())
+            .chain(extras)
+            .collect()
+    }
+
     pub fn display_timeout(&self) -> Duration {
         Duration::from_secs(self.display.screen_timeout)
     }
@@ -102,26 +1288,571 @@ impl Config {
         Duration::from_secs(self.display.periodic_off_duration)
     }
 
+    /// Loop interval, never shorter than `MIN_REFRESH_INTERVAL_MS`.
     pub fn refresh_interval(&self) -> Duration {
-        Duration::from_millis(self.display.refresh_interval_ms)
+        Duration::from_millis(
+            self.display
+                .refresh_interval_ms
+                .max(MIN_REFRESH_INTERVAL_MS),
+        )
+    }
+
+    pub fn page_interval(&self) -> Duration {
+        Duration::from_secs(self.display.page_interval_secs)
+    }
+
+    pub fn dim_fade(&self) -> Duration {
+        Duration::from_secs(self.display.dim_fade_secs)
+    }
+
+    /// Start and end of the display-off window in minutes after midnight.
+    pub fn night_window(&self) -> Option<(u32, u32)> {
+        let start = parse_time_of_day(self.schedule.off_start.as_deref()?)?;
+        let end = parse_time_of_day(self.schedule.off_end.as_deref()?)?;
+        Some((start, end))
     }
+
+    pub fn sparkline_interval(&self) -> Duration {
+        Duration::from_secs(self.display.sparkline_interval_secs)
+    }
+
+    pub fn splash_duration(&self) -> Duration {
+        Duration::from_secs(self.display.splash_duration_secs)
+    }
+
+    /// `None` when the displays are only re-initialized after errors.
+    pub fn reinit_interval(&self) -> Option<Duration> {
+        let secs = self.display.reinit_interval_secs;
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    pub fn shift_interval(&self) -> Duration {
+        Duration::from_secs(self.display.shift_interval_secs)
+    }
+
+    pub fn ip_skip_ranges(&self) -> Vec<IpRange> {
+        self.network
+            .ip_skip_ranges
+            .iter()
+            .filter_map(|range| range.parse().ok())
+            .collect()
+    }
+
+    /// Bounds of the random shift; `None` with `shift_mode = "pattern"`.
+    pub fn shift_range(&self) -> Option<Point> {
+        let (x, y) = self.display.shift_range;
+        (self.display.shift_mode == "random").then(|| Point::new(x, y))
+    }
+
+    pub fn shift_pattern(&self) -> Vec<Point> {
+        self.display
+            .shift_pattern
+            .iter()
+            .map(|&(x, y)| Point::new(x, y))
+            .collect()
+    }
+}
+
+impl DisplayConfig {
+    pub fn use_fahrenheit(&self) -> bool {
+        self.temp_unit.eq_ignore_ascii_case("F")
+    }
+
+    /// `enable_periodic_off`, unless `reduced_motion` rules it out.
+    pub fn periodic_off(&self) -> bool {
+        self.enable_periodic_off && !self.reduced_motion
+    }
+}
+
+impl FanConfig {
+    /// Temperature at which the fan turns on. Always set once the config
+    /// is loaded.
+    pub fn temp_on(&self) -> f32 {
+        self.temp_on.unwrap_or(board::fan_thresholds().0)
+    }
+
+    /// Temperature at which the fan turns off: `temp_off`, or `hysteresis`
+    /// degrees below `temp_on`.
+    pub fn temp_off(&self) -> f32 {
+        match (self.temp_off, self.hysteresis) {
+            (Some(temp_off), _) => temp_off,
+            (None, Some(hysteresis)) => self.temp_on() - hysteresis,
+            (None, None) => self.temp_on(),
+        }
+    }
+
+    /// Fills in the thresholds for the detected board when `temp_on` is not
+    /// set. Unless `temp_off` or `hysteresis` is set, `temp_off` is put the
+    /// board's gap below `temp_on`, also when `temp_on` comes from the file.
+    fn apply_board_defaults(&mut self, fahrenheit: bool) {
+        let unit_scale = if fahrenheit { 9.0 / 5.0 } else { 1.0 };
+        let (board_on, board_off) = board::fan_thresholds();
+        let temp_on = match self.temp_on {
+            Some(temp_on) => temp_on,
+            None => {
+                info!(
+                    "fan.temp_on not set, using {}°C for {}",
+                    board_on,
+                    board::model().unwrap_or("an unknown board")
+                );
+                let temp_on = if fahrenheit {
+                    board_on * unit_scale + 32.0
+                } else {
+                    board_on
+                };
+                self.temp_on = Some(temp_on);
+                temp_on
+            }
+        };
+        if self.temp_off.is_none() && self.hysteresis.is_none() {
+            let gap = (board_on - board_off) * unit_scale;
+            self.temp_off = Some(temp_on - gap);
+            debug!("fan.temp_off not set, using {} below fan.temp_on", gap);
+        }
+    }
+
+    /// Converts thresholds written in Fahrenheit to the Celsius the
+    /// temperature sensors report.
+    fn thresholds_to_celsius(&mut self) {
+        self.temp_on = self.temp_on.map(fahrenheit_to_celsius);
+        self.temp_off = self.temp_off.map(fahrenheit_to_celsius);
+        self.hysteresis = self.hysteresis.map(|hysteresis| hysteresis * 5.0 / 9.0);
+        self.temp_critical = self.temp_critical.map(fahrenheit_to_celsius);
+        for point in &mut self.curve {
+            point.0 = fahrenheit_to_celsius(point.0);
+        }
+    }
+
+    /// Undoes `thresholds_to_celsius`.
+    fn thresholds_to_fahrenheit(&mut self) {
+        self.temp_on = self.temp_on.map(celsius_to_fahrenheit);
+        self.temp_off = self.temp_off.map(celsius_to_fahrenheit);
+        self.hysteresis = self
+            .hysteresis
+            .map(|hysteresis| round_hundredths(hysteresis * 9.0 / 5.0));
+        self.temp_critical = self.temp_critical.map(celsius_to_fahrenheit);
+        for point in &mut self.curve {
+            point.0 = celsius_to_fahrenheit(point.0);
+        }
+    }
+}
+
+fn fahrenheit_to_celsius(fahrenheit: f32) -> f32 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+/// Rounded to hundredths, so e.g. 150°F comes back from Celsius as 150
+/// rather than 150.00002.
+fn celsius_to_fahrenheit(celsius: f32) -> f32 {
+    round_hundredths(celsius * 9.0 / 5.0 + 32.0)
+}
+
+fn round_hundredths(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
 }
 
 impl Default for Config {
     fn default() -> Self {
+        let (temp_on, temp_off) = board::fan_thresholds();
         Config {
             display: DisplayConfig {
                 brightness: default_brightness(),
+                contrast: None,
                 screen_timeout: default_screen_timeout(),
+                always_on: false,
+                reduced_motion: false,
                 enable_periodic_off: default_periodic_off(),
                 periodic_on_duration: default_periodic_on_duration_seconds(),
                 periodic_off_duration: default_periodic_off_duration_seconds(),
                 refresh_interval_ms: default_refresh_interval_ms(),
+                rotation: default_rotation(),
+                layout: default_layout(),
+                ip_align: default_center_align(),
+                hostname_align: default_center_align(),
+                value_align: default_value_align(),
+                invert_colors: false,
+                size: default_display_size(),
+                temp_unit: default_temp_unit(),
+                page_interval_secs: default_page_interval_secs(),
+                show_bars: default_show_bars(),
+                show_ip: default_show_field(),
+                show_cpu: default_show_field(),
+                show_temp: default_show_field(),
+                show_ram: default_show_field(),
+                show_rpm: default_show_field(),
+                show_hostname: default_show_field(),
+                show_cpu_freq: false,
+                normalize_load: default_normalize_load(),
+                scroll_speed: default_scroll_speed(),
+                decimals: default_decimals(),
+                cpu_average_samples: default_cpu_average_samples(),
+                mem_format: default_mem_format(),
+                qr_template: None,
+                summary_fields: Vec::new(),
+                summary_separator: default_summary_separator(),
+                emphasize: None,
+                cooldown_page: false,
+                show_clock: false,
+                time_format: default_time_format(),
+                font: default_font(),
+                sparkline_samples: 0,
+                sparkline_interval_secs: default_sparkline_interval_secs(),
+                shift_pattern: default_shift_pattern(),
+                dim_fade_secs: default_dim_fade_secs(),
+                activity_temp_delta: 0.0,
+                shift_interval_secs: default_shift_interval_secs(),
+                shift_mode: default_shift_mode(),
+                shift_range: default_shift_range(),
+                shift_seed: None,
+                splash_duration_secs: default_splash_duration_secs(),
+                init_attempts: default_init_attempts(),
+                reinit_interval_secs: 0,
+                i2c_bus: default_i2c_bus(),
+                i2c_address: default_i2c_address(),
+                page: None,
             },
             fan: FanConfig {
-                temp_on: 60.0,
-                temp_off: 50.0,
+                temp_on: Some(temp_on),
+                temp_off: Some(temp_off),
+                hysteresis: None,
+                curve: Vec::new(),
+                tach_pin: None,
+                gpio_pin: default_fan_gpio_pin(),
+                invert: default_fan_invert(),
+                min_run_secs: default_min_run_secs(),
+                kickstart_ms: 0,
+                boot_behavior: default_boot_behavior(),
+                max_cool_secs: default_max_cool_secs(),
+                pwm_hardware: false,
+                pwm_channel: 0,
+                pwm_freq_hz: default_pwm_freq_hz(),
+                temp_smoothing: default_temp_smoothing(),
+                temp_critical: None,
+                critical_readings: default_critical_readings(),
+                critical_command: default_critical_command(),
+                on_fan_on: None,
+                on_fan_off: None,
             },
+            network: NetworkConfig::default(),
+            disk: DiskConfig::default(),
+            memory: MemoryConfig::default(),
+            power: PowerConfig::default(),
+            metrics: MetricsConfig::default(),
+            control: ControlConfig::default(),
+            status_socket: StatusSocketConfig::default(),
+            logging: LoggingConfig::default(),
+            log: LogConfig::default(),
+            alerts: AlertsConfig::default(),
+            temperature: TemperatureConfig::default(),
+            button: ButtonConfig::default(),
+            heartbeat: HeartbeatConfig::default(),
+            schedule: ScheduleConfig::default(),
+            mqtt: MqttConfig::default(),
+            extra_displays: Vec::new(),
+        }
+    }
+}
+
+/// Minutes after midnight for a time such as `"22:30"`.
+fn parse_time_of_day(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.split_once(':')?;
+    let hours: u32 = hours.trim().parse().ok()?;
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+impl TemperatureConfig {
+    /// `temp_sensor_path` followed by the extra sensors.
+    pub fn sensor_paths(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.temp_sensor_path.as_str())
+            .chain(self.extra_sensor_paths.iter().map(String::as_str))
+    }
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        ButtonConfig {
+            gpio_pin: None,
+            active_low: default_button_active_low(),
+            debounce_ms: default_button_debounce_ms(),
+        }
+    }
+}
+
+impl Default for TemperatureConfig {
+    fn default() -> Self {
+        TemperatureConfig {
+            temp_source: default_temp_source(),
+            temp_command: None,
+            temp_sensor_path: default_temp_sensor_path(),
+            extra_sensor_paths: Vec::new(),
+            combine: default_temp_combine(),
+            temp_divisor: default_temp_divisor(),
+            gpu: false,
+            fan_include_gpu: false,
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            file: None,
+            interval_secs: default_logging_interval_secs(),
+            format: default_logging_format(),
+            max_size_kb: default_logging_max_size_kb(),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            file: None,
+            level: default_log_level(),
+            max_size_kb: default_log_max_size_kb(),
+        }
+    }
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            webhook_url: None,
+            temp_above: None,
+            ram_above: None,
+            disk_above: None,
+            debounce_secs: default_alerts_debounce_secs(),
+        }
+    }
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        ControlConfig {
+            enabled: false,
+            port: default_control_port(),
+            token: None,
+            override_secs: default_override_secs(),
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            enabled: default_metrics_enabled(),
+            port: default_metrics_port(),
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            net_interface: None,
+            ip_interface: None,
+            ip_version: default_ip_version(),
+            ip_skip_ranges: default_ip_skip_ranges(),
+            ip_change_flash_secs: 0,
+            startup_delay_secs: 0,
+        }
+    }
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        PowerConfig {
+            i2c_address: None,
+            i2c_bus: default_i2c_bus(),
+            shunt_ohms: default_shunt_ohms(),
+        }
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig {
+            swap_warn_threshold: default_swap_warn_threshold(),
+        }
+    }
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        DiskConfig {
+            mount_point: default_disk_mount_point(),
+            warn_threshold: default_disk_warn_threshold(),
         }
     }
 }
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        MqttConfig {
+            enabled: default_mqtt_enabled(),
+            host: default_mqtt_host(),
+            port: default_mqtt_port(),
+            topic_prefix: default_mqtt_topic_prefix(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `toml` as a config file, without the developer's `RUSTBERRY_*`
+    /// variables.
+    fn load(name: &str, toml: &str) -> Result<Config, String> {
+        load_with_env(name, toml, &[])
+    }
+
+    fn load_with_env(name: &str, toml: &str, vars: &[(&str, &str)]) -> Result<Config, String> {
+        let path = env::temp_dir().join(format!(
+            "rustberry-config-test-{}-{}.toml",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, toml).unwrap();
+        let vars = vars
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()));
+        let config = Config::load_with_env(&path, false, vars).map_err(|e| e.to_string());
+        fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn converts_fahrenheit_thresholds_to_celsius() {
+        let config = load(
+            "fahrenheit",
+            "[display]\ntemp_unit = \"F\"\n\
+             [fan]\ntemp_on = 140.0\ntemp_off = 122.0\ntemp_critical = 176.0\n\
+             curve = [[104.0, 30], [140.0, 100]]\n\
+             [alerts]\ntemp_above = 167.0\n",
+        )
+        .unwrap();
+        assert!((config.fan.temp_on() - 60.0).abs() < 0.001);
+        assert!((config.fan.temp_off() - 50.0).abs() < 0.001);
+        assert!((config.fan.temp_critical.unwrap() - 80.0).abs() < 0.001);
+        assert!((config.fan.curve[0].0 - 40.0).abs() < 0.001);
+        assert!((config.alerts.temp_above.unwrap() - 75.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn converts_fahrenheit_hysteresis_as_a_difference() {
+        let config = load(
+            "hysteresis",
+            "[display]\ntemp_unit = \"F\"\n[fan]\ntemp_on = 140.0\nhysteresis = 18.0\n",
+        )
+        .unwrap();
+        assert!((config.fan.temp_off() - 50.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn keeps_celsius_thresholds() {
+        let config = load(
+            "celsius",
+            "[display]\n[fan]\ntemp_on = 60.0\ntemp_off = 50.0\n",
+        )
+        .unwrap();
+        assert_eq!(config.fan.temp_on(), 60.0);
+        assert_eq!(config.fan.temp_off(), 50.0);
+    }
+
+    #[test]
+    fn validates_fahrenheit_thresholds_in_celsius() {
+        // 20°F and 10°F are below freezing, though positive as written.
+        let error = load(
+            "below-freezing",
+            "[display]\ntemp_unit = \"F\"\n[fan]\ntemp_on = 20.0\ntemp_off = 10.0\n",
+        )
+        .unwrap_err();
+        assert!(error.contains("must be above 0°C"), "{}", error);
+    }
+
+    #[test]
+    fn into_toml_writes_thresholds_back_in_fahrenheit() {
+        let config = load(
+            "round-trip",
+            "[display]\ntemp_unit = \"F\"\n[fan]\ntemp_on = 140.0\ntemp_off = 122.0\n",
+        )
+        .unwrap();
+        let table: toml::Table = toml::from_str(&config.into_toml().unwrap()).unwrap();
+        assert_eq!(table["fan"]["temp_on"].as_float(), Some(140.0));
+        assert_eq!(table["fan"]["temp_off"].as_float(), Some(122.0));
+    }
+
+    #[test]
+    fn missing_file_is_only_created_when_asked_for() {
+        let path = env::temp_dir().join(format!(
+            "rustberry-config-test-{}-missing.toml",
+            std::process::id()
+        ));
+        // As `read_from` loads it.
+        let config = Config::load_with_env(&path, false, std::iter::empty()).unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            config.display.brightness,
+            Config::default().display.brightness
+        );
+
+        // As `load_from` loads it.
+        Config::load_with_env(&path, true, std::iter::empty()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn temp_on_alone_gets_the_board_gap() {
+        let (board_on, board_off) = board::fan_thresholds();
+        let gap = board_on - board_off;
+        let config = load("temp-on-only", "[display]\n[fan]\ntemp_on = 70.0\n").unwrap();
+        assert_eq!(config.fan.temp_on(), 70.0);
+        assert_eq!(config.fan.temp_off(), 70.0 - gap);
+
+        let config = load(
+            "temp-on-only-fahrenheit",
+            "[display]\ntemp_unit = \"F\"\n[fan]\ntemp_on = 158.0\n",
+        )
+        .unwrap();
+        assert!((config.fan.temp_on() - 70.0).abs() < 0.001);
+        assert!((config.fan.temp_off() - (70.0 - gap)).abs() < 0.001);
+    }
+
+    #[test]
+    fn hysteresis_is_not_overridden_by_the_board_gap() {
+        let config = load(
+            "temp-on-hysteresis",
+            "[display]\n[fan]\ntemp_on = 70.0\nhysteresis = 4.0\n",
+        )
+        .unwrap();
+        assert_eq!(config.fan.temp_off, None);
+        assert_eq!(config.fan.temp_off(), 66.0);
+    }
+
+    #[test]
+    fn env_overrides_take_precedence_over_the_file() {
+        let config = load_with_env(
+            "env",
+            "[display]\nbrightness = 1\n[fan]\ntemp_on = 60.0\ntemp_off = 50.0\n",
+            &[
+                ("RUSTBERRY_FAN_TEMP_ON", "65"),
+                ("RUSTBERRY_DISPLAY_BRIGHTNESS", "3"),
+                ("RUSTBERRY_STATUS_SOCKET_PATH", "/run/monitor.sock"),
+                ("RUSTBERRY_NO_SUCH_SECTION", "1"),
+                ("HOME", "/root"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.fan.temp_on(), 65.0);
+        assert_eq!(config.display.brightness, 3);
+        assert_eq!(
+            config.status_socket.path.as_deref(),
+            Some("/run/monitor.sock")
+        );
+    }
+}
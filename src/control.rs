@@ -0,0 +1,231 @@
+use crate::config::ControlConfig;
+use crate::http::{read_line_capped, request_reader};
+use log::{debug, info, warn};
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MAX_HEADER_LINES: usize = 64;
+
+/// State forced by a control request until `until`.
+#[derive(Clone, Copy)]
+struct Override {
+    on: bool,
+    until: Instant,
+}
+
+#[derive(Default)]
+struct Overrides {
+    display: Option<Override>,
+    fan: Option<Override>,
+}
+
+/// Small HTTP server for switching the display and fan by hand, e.g.
+/// `curl -X POST http://<pi>:9102/fan/on`. The main loop polls the active
+/// overrides; each one ends after `override_secs` or with `/display/auto`
+/// and `/fan/auto`. Without a `token` it only listens on localhost.
+pub struct ControlServer {
+    overrides: Arc<Mutex<Overrides>>,
+}
+
+impl ControlServer {
+    /// Binds the control port and spawns the server thread. Returns `None` if
+    /// the port cannot be bound, so the monitor keeps running without it.
+    pub fn start(control_config: &ControlConfig) -> Option<Self> {
+        let port = control_config.port;
+        let address = if control_config.token.is_some() {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        let listener = match TcpListener::bind((address, port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!(
+                    "Failed to bind control server on port {}: {}. Continuing without it.",
+                    port, e
+                );
+                return None;
+            }
+        };
+        info!("Control server listening on {}:{}", address, port);
+        if control_config.token.is_none() {
+            warn!("Control server has no token, so it only accepts requests from this host");
+        }
+
+        let overrides = Arc::new(Mutex::new(Overrides::default()));
+        let server_overrides = Arc::clone(&overrides);
+        let token = control_config.token.clone();
+        let duration = Duration::from_secs(control_config.override_secs);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let request = Request {
+                            overrides: &server_overrides,
+                            token: token.as_deref(),
+                            duration,
+                        };
+                        if let Err(e) = request.handle(stream) {
+                            debug!("Control connection error: {}", e);
+                        }
+                    }
+                    Err(e) => debug!("Failed to accept control connection: {}", e),
+                }
+            }
+        });
+
+        Some(ControlServer { overrides })
+    }
+
+    /// Forced display state, or `None` when the display follows the config.
+    pub fn display_override(&self) -> Option<bool> {
+        active("display", &mut self.overrides.lock().unwrap().display)
+    }
+
+    /// Forced fan state, or `None` when the fan follows the temperature.
+    /// A forced off ends once `temp` reaches `temp_on`, so the CPU does not
+    /// heat up unattended; `temp_critical` is always above `temp_on`.
+    pub fn fan_override(&self, temp: f32, temp_on: f32) -> Option<bool> {
+        fan_override(&mut self.overrides.lock().unwrap().fan, temp, temp_on)
+    }
+}
+
+fn fan_override(slot: &mut Option<Override>, temp: f32, temp_on: f32) -> Option<bool> {
+    let on = active("fan", slot)?;
+    if !on && temp >= temp_on {
+        warn!("CPU at {:.1}°C, ending the manual fan off override", temp);
+        *slot = None;
+        return None;
+    }
+    Some(on)
+}
+
+fn active(name: &str, slot: &mut Option<Override>) -> Option<bool> {
+    let current = (*slot)?;
+    if Instant::now() < current.until {
+        return Some(current.on);
+    }
+    info!("Manual {} override expired", name);
+    *slot = None;
+    None
+}
+
+struct Request<'a> {
+    overrides: &'a Mutex<Overrides>,
+    token: Option<&'a str>,
+    duration: Duration,
+}
+
+impl Request<'_> {
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = request_reader(&stream)?;
+        let mut request_line = String::new();
+        read_line_capped(&mut reader, &mut request_line)?;
+
+        let mut authorized = self.token.is_none();
+        for _ in 0..MAX_HEADER_LINES {
+            let mut header = String::new();
+            if read_line_capped(&mut reader, &mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let (Some(token), Some((name, value))) = (self.token, header.split_once(':')) {
+                if name.trim().eq_ignore_ascii_case("authorization")
+                    && value.trim().strip_prefix("Bearer ") == Some(token)
+                {
+                    authorized = true;
+                }
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("");
+        let status = if !authorized {
+            "401 Unauthorized"
+        } else if method != "POST" {
+            "405 Method Not Allowed"
+        } else if self.apply(path) {
+            "200 OK"
+        } else {
+            "404 Not Found"
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            status
+        );
+        stream.write_all(response.as_bytes())
+    }
+
+    /// Sets or clears the override for `path`; false for unknown paths.
+    fn apply(&self, path: &str) -> bool {
+        let Some((target, action)) = path.trim_start_matches('/').split_once('/') else {
+            return false;
+        };
+        let new = match action {
+            "on" => Some(Override {
+                on: true,
+                until: Instant::now() + self.duration,
+            }),
+            "off" => Some(Override {
+                on: false,
+                until: Instant::now() + self.duration,
+            }),
+            "auto" => None,
+            _ => return false,
+        };
+        let mut overrides = self.overrides.lock().unwrap();
+        let slot = match target {
+            "display" => &mut overrides.display,
+            "fan" => &mut overrides.fan,
+            _ => return false,
+        };
+        info!("Control request: {} {}", target, action);
+        *slot = new;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forced(on: bool) -> Option<Override> {
+        Some(Override {
+            on,
+            until: Instant::now() + Duration::from_secs(60),
+        })
+    }
+
+    #[test]
+    fn fan_off_override_holds_below_temp_on() {
+        let mut slot = forced(false);
+        assert_eq!(fan_override(&mut slot, 59.9, 60.0), Some(false));
+        assert!(slot.is_some());
+    }
+
+    #[test]
+    fn fan_off_override_ends_at_temp_on() {
+        let mut slot = forced(false);
+        assert_eq!(fan_override(&mut slot, 60.0, 60.0), None);
+        assert!(slot.is_none());
+    }
+
+    #[test]
+    fn fan_on_override_holds_when_hot() {
+        let mut slot = forced(true);
+        assert_eq!(fan_override(&mut slot, 90.0, 60.0), Some(true));
+    }
+
+    #[test]
+    fn expired_override_is_cleared() {
+        let mut slot = Some(Override {
+            on: true,
+            until: Instant::now(),
+        });
+        assert_eq!(fan_override(&mut slot, 40.0, 60.0), None);
+        assert!(slot.is_none());
+    }
+}
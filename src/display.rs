@@ -1,87 +1,1626 @@
 use crate::config::DisplayConfig as AppDisplayConfig;
-use crate::display_types::{Display, FONT_5X8, FONT_6X12, PCSENIOR8_STYLE, PROFONT12};
+use crate::display_types::{
+    Display, Panel, PanelDriver, FONT_4X6, FONT_5X8, FONT_5X8_INVERTED, FONT_6X10,
+    FONT_6X10_INVERTED, FONT_6X12, FONT_6X13_BOLD, FONT_8X13_BOLD, FONT_9X15, FONT_9X15_BOLD,
+    FONT_9X15_INVERTED, PCSENIOR8_INVERTED_STYLE, PCSENIOR8_STYLE, PROFONT12,
+};
+use crate::simulator::SimulatedPanel;
+use crate::SystemStats;
 use display_interface::DisplayError;
-use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, text::Text};
+use embedded_graphics::{
+    image::{Image, ImageRaw},
+    mono_font::MonoTextStyle,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder},
+};
 use linux_embedded_hal::I2cdev;
 use log::{debug, info, warn};
+use qrcodegen::{QrCode, QrCodeEcc};
+use std::path::{Path, PathBuf};
 
 use ssd1306::mode::DisplayConfig;
 use ssd1306::prelude::Brightness;
 use ssd1306::{prelude::*, I2CDisplayInterface, Ssd1306};
 
+/// Runs `$body` with `$panel` bound to the concrete driver of `$display`.
+macro_rules! with_panel {
+    ($display:expr, $panel:ident => $body:expr) => {
+        match $display {
+            Display::Size128x32($panel) => $body,
+            Display::Size128x64($panel) => $body,
+            Display::Simulated($panel) => $body,
+        }
+    };
+}
+
+/// Info pages the display can cycle through.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Page {
+    Stats,
+    System,
+    Disk,
+    Network,
+    Cores,
+    Power,
+    Qr,
+    Clock,
+    /// Shown instead of the cycled page while the fan runs, with
+    /// `cooldown_page`.
+    Cooldown,
+}
+
+impl Page {
+    pub const ALL: [Page; 9] = [
+        Page::Stats,
+        Page::System,
+        Page::Disk,
+        Page::Network,
+        Page::Cores,
+        Page::Power,
+        Page::Qr,
+        Page::Clock,
+        Page::Cooldown,
+    ];
+
+    /// Pages shown by `page_interval_secs`; the power page only when a
+    /// power sensor was found, the QR code page only with a `qr_template`
+    /// and the clock page only with `show_clock`.
+    pub fn cycle(power: bool, qr: bool, clock: bool) -> Vec<Page> {
+        Page::ALL
+            .into_iter()
+            .filter(|&page| match page {
+                Page::Power => power,
+                Page::Qr => qr,
+                Page::Clock => clock,
+                Page::Cooldown => false,
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Page for a config value such as `"network"`.
+    pub fn from_name(name: &str) -> Option<Page> {
+        match name {
+            "stats" => Some(Page::Stats),
+            "system" => Some(Page::System),
+            "disk" => Some(Page::Disk),
+            "network" => Some(Page::Network),
+            "cores" => Some(Page::Cores),
+            "power" => Some(Page::Power),
+            "qr" => Some(Page::Qr),
+            "clock" => Some(Page::Clock),
+            "cooldown" => Some(Page::Cooldown),
+            _ => None,
+        }
+    }
+}
+
+/// Config-driven choices the draw functions need besides the stats.
+#[derive(Clone, Copy)]
+struct LayoutOptions {
+    temp_unit: &'static str,
+    show_bars: bool,
+    fields: StatsFields,
+    /// `display.reduced_motion`: the fan glyph stands still.
+    reduced_motion: bool,
+    scroll_speed: u32,
+    /// Forced arrangement; `None` picks portrait for panels taller than wide.
+    portrait: Option<bool>,
+    /// Alignment of the lines and value columns on the landscape stats page.
+    ip_align: Alignment,
+    hostname_align: Alignment,
+    value_align: Alignment,
+    /// Font of the values on the landscape pages, plain and highlighted.
+    value_style: MonoTextStyle<'static, BinaryColor>,
+    value_style_inverted: MonoTextStyle<'static, BinaryColor>,
+    /// Bold font as wide as `value_style`, for the emphasized value.
+    value_style_bold: MonoTextStyle<'static, BinaryColor>,
+    /// `display.emphasize`: the stats page value drawn in bold.
+    emphasized: Option<Field>,
+    /// Pixels the marquee has advanced in the current frame; `None` when
+    /// scrolling is off and long text is cut off instead.
+    scroll: Option<u32>,
+}
+
+/// Fields shown on the stats page.
+#[derive(Clone, Copy)]
+struct StatsFields {
+    ip: bool,
+    cpu: bool,
+    temp: bool,
+    ram: bool,
+    rpm: bool,
+    hostname: bool,
+}
+
+/// Values of the stats page that can be emphasized.
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Cpu,
+    Temp,
+    Ram,
+    Rpm,
+}
+
+impl LayoutOptions {
+    fn from_config(display_config: &AppDisplayConfig) -> Self {
+        let (value_style, value_style_inverted, value_style_bold) =
+            match display_config.font.as_str() {
+                "6x10" => (FONT_6X10, FONT_6X10_INVERTED, FONT_6X13_BOLD),
+                "9x15" => (FONT_9X15, FONT_9X15_INVERTED, FONT_9X15_BOLD),
+                _ => (PCSENIOR8_STYLE, PCSENIOR8_INVERTED_STYLE, FONT_8X13_BOLD),
+            };
+        LayoutOptions {
+            temp_unit: if display_config.use_fahrenheit() {
+                "F"
+            } else {
+                "C"
+            },
+            show_bars: display_config.show_bars,
+            fields: StatsFields {
+                ip: display_config.show_ip,
+                cpu: display_config.show_cpu,
+                temp: display_config.show_temp,
+                ram: display_config.show_ram,
+                rpm: display_config.show_rpm,
+                hostname: display_config.show_hostname,
+            },
+            reduced_motion: display_config.reduced_motion,
+            scroll_speed: if display_config.reduced_motion {
+                0
+            } else {
+                display_config.scroll_speed
+            },
+            portrait: match display_config.layout.as_str() {
+                "landscape" => Some(false),
+                "portrait" => Some(true),
+                _ => None,
+            },
+            ip_align: map_alignment_value(&display_config.ip_align),
+            hostname_align: map_alignment_value(&display_config.hostname_align),
+            value_align: map_alignment_value(&display_config.value_align),
+            value_style,
+            value_style_inverted,
+            value_style_bold,
+            emphasized: match display_config.emphasize.as_deref() {
+                Some("cpu") => Some(Field::Cpu),
+                Some("temp") => Some(Field::Temp),
+                Some("ram") => Some(Field::Ram),
+                Some("rpm") => Some(Field::Rpm),
+                _ => None,
+            },
+            scroll: None,
+        }
+    }
+}
+
 pub struct PoeDisplay {
     display: Display,
+    /// Startup settings and address, used to re-initialize the panel.
+    display_config: AppDisplayConfig,
+    address: u8,
+    /// Directory the frames are written to instead of a panel (`--simulate`).
+    frames_dir: Option<PathBuf>,
+    /// Configured brightness level, 0 (dimmest) to 4 (brightest).
+    brightness: u8,
+    /// Contrast replacing the one of the brightness level.
+    contrast: Option<u8>,
+    layout: LayoutOptions,
+    page: Option<Page>,
+    scroll_position: u32,
+    /// Alternates the fan icon between its two frames.
+    fan_frame: bool,
 }
 
 impl PoeDisplay {
-    pub fn new(display_config: &AppDisplayConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        let i2c = I2cdev::new("/dev/i2c-1")?;
-        let display = initialize_display(i2c, display_config)?;
-        Ok(PoeDisplay { display })
+    pub fn new(
+        display_config: &AppDisplayConfig,
+        address: u8,
+        frames_dir: Option<&Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if frames_dir.is_none() {
+            info!(
+                "Opening display on I2C bus {} at address 0x{:02X}",
+                display_config.i2c_bus, address
+            );
+        }
+        let display = open_display(display_config, address, frames_dir)?;
+        let layout = LayoutOptions::from_config(display_config);
+        let mut poe_disp = PoeDisplay {
+            display,
+            display_config: display_config.clone(),
+            address,
+            frames_dir: frames_dir.map(Path::to_path_buf),
+            brightness: display_config.brightness,
+            contrast: None,
+            layout,
+            page: assigned_page(display_config),
+            scroll_position: 0,
+            fan_frame: false,
+        };
+        if let Some(contrast) = display_config.contrast {
+            poe_disp
+                .set_contrast(contrast)
+                .map_err(|e| format!("Failed to set display contrast: {:?}", e))?;
+        }
+        Ok(poe_disp)
+    }
+
+    /// Re-opens the I2C bus and initializes the panel again, e.g. after a
+    /// bus glitch. Brightness is restored to the configured level.
+    pub fn reconnect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Reconnecting display at address 0x{:02X}", self.address);
+        self.display = open_display(
+            &self.display_config,
+            self.address,
+            self.frames_dir.as_deref(),
+        )?;
+        self.restore_brightness()
+            .map_err(|e| format!("Failed to restore display brightness: {:?}", e))?;
+        Ok(())
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Page pinned to this display, or `None` when it follows the page cycle.
+    pub fn page(&self) -> Option<Page> {
+        self.page
+    }
+
+    /// Picks up settings that can change without re-initializing the panel.
+    /// Size and rotation are only applied at startup.
+    pub fn reconfigure(&mut self, display_config: &AppDisplayConfig) {
+        if display_config.invert_colors != self.display_config.invert_colors {
+            if let Err(e) = self.set_invert(display_config.invert_colors) {
+                warn!("Failed to change display inversion: {:?}", e);
+            }
+            self.display_config.invert_colors = display_config.invert_colors;
+        }
+        if display_config.brightness != self.brightness {
+            info!(
+                "Display 0x{:02X} brightness set to {:?}",
+                self.address,
+                map_brightness_value(display_config.brightness)
+            );
+        }
+        self.brightness = display_config.brightness;
+        self.contrast = display_config.contrast;
+        self.layout = LayoutOptions::from_config(display_config);
+        self.page = assigned_page(display_config);
     }
 
     pub fn set_brightness(&mut self, brightness: Brightness) -> Result<(), DisplayError> {
-        self.display.set_brightness(brightness)
+        with_panel!(&mut self.display, disp => disp.set_brightness(brightness))
+    }
+
+    /// Swaps lit and unlit pixels in the panel itself, so the frame buffer,
+    /// pixel shift and dimming are unaffected.
+    pub fn set_invert(&mut self, invert: bool) -> Result<(), DisplayError> {
+        with_panel!(&mut self.display, disp => disp.set_invert(invert))
+    }
+
+    /// Sets the panel contrast (0-255) in place of the one of the brightness
+    /// level. The screen timeout still dims the panel from there.
+    pub fn set_contrast(&mut self, contrast: u8) -> Result<(), DisplayError> {
+        self.contrast = Some(contrast);
+        self.restore_brightness()
+    }
+
+    /// Returns to the configured brightness, e.g. after the screen timeout
+    /// dimmed the panel.
+    pub fn restore_brightness(&mut self) -> Result<(), DisplayError> {
+        self.fade_brightness(0.0)
+    }
+
+    /// Steps from the configured brightness towards `DIMMEST` as `progress`
+    /// goes from 0.0 to 1.0. A configured contrast fades out alongside.
+    pub fn fade_brightness(&mut self, progress: f32) -> Result<(), DisplayError> {
+        let remaining = 1.0 - progress.clamp(0.0, 1.0);
+        let level = (self.brightness as f32 * remaining).round() as u8;
+        let brightness = match self.contrast {
+            Some(contrast) => {
+                // Levels above the dimmest share the longer pre-charge period.
+                let precharge = if level == 0 { 1 } else { 2 };
+                Brightness::custom(precharge, (contrast as f32 * remaining).round() as u8)
+            }
+            None => map_brightness_value(level),
+        };
+        self.set_brightness(brightness)
     }
 
     pub fn display_off(&mut self) -> Result<(), DisplayError> {
         debug!("Turning display OFF.");
-        self.display.set_display_on(false)
+        with_panel!(&mut self.display, disp => disp.set_display_on(false))
     }
 
     pub fn display_on(&mut self) -> Result<(), DisplayError> {
         debug!("Turning display ON.");
-        self.display.set_display_on(true)
+        with_panel!(&mut self.display, disp => disp.set_display_on(true))
     }
 
-    pub fn update(
+    /// Blanks the panel and switches it off.
+    pub fn clear(&mut self) -> Result<(), DisplayError> {
+        debug!("Clearing display.");
+        with_panel!(&mut self.display, disp => {
+            disp.clear(BinaryColor::Off)?;
+            disp.flush()?;
+            disp.set_display_on(false)
+        })
+    }
+
+    /// Replaces the current page with a warning, e.g. before an emergency
+    /// shutdown. The title is drawn inverted above the detail line.
+    pub fn show_alert(&mut self, title: &str, detail: &str) -> Result<(), DisplayError> {
+        let lines = [
+            (title, FONT_5X8_INVERTED),
+            ("", FONT_4X6),
+            (detail, FONT_5X8),
+        ];
+        with_panel!(&mut self.display, disp => draw_centered(disp, &lines))
+    }
+
+    /// Shows the project name and `version` until the next page is drawn.
+    pub fn show_splash(&mut self, version: &str) -> Result<(), DisplayError> {
+        let version = format!("v{}", version);
+        let lines = [
+            ("RustBerry", FONT_6X12),
+            ("PoE Monitor", FONT_5X8),
+            ("", FONT_4X6),
+            (version.as_str(), FONT_4X6),
+        ];
+        with_panel!(&mut self.display, disp => draw_centered(disp, &lines))
+    }
+
+    pub fn update_page(
         &mut self,
-        ip_address: &str,
-        cpu_usage: String,
-        temp: String,
-        ram_usage: String,
-        hostname: &str,
+        page: Page,
+        stats: &SystemStats,
+        fan_running: bool,
         offset: Point,
     ) -> Result<(), DisplayError> {
-        let disp = &mut self.display;
+        let mut layout = self.layout;
+        let fan_frame = self.fan_frame;
+        self.fan_frame = fan_running && !fan_frame && !layout.reduced_motion;
+        if layout.scroll_speed > 0 {
+            layout.scroll = Some(self.scroll_position);
+            self.scroll_position = self.scroll_position.wrapping_add(layout.scroll_speed);
+        }
+        let fan_icon = fan_running.then_some(fan_frame);
+        with_panel!(&mut self.display, disp => {
+            draw_page(disp, page, stats, &layout, fan_icon, offset)
+        })
+    }
+}
+
+/// Baselines of the rows used by the landscape layouts. The 15px font only
+/// fits on 64px tall panels, where its rows are spread further apart.
+fn landscape_rows<D: Dimensions>(disp: &D, layout: &LayoutOptions) -> (i32, i32, i32) {
+    if disp.bounding_box().size.height < 64 {
+        (7, 19, 30)
+    } else if layout.value_style.font.character_size.height > 10 {
+        (13, 33, 51)
+    } else {
+        (10, 28, 44)
+    }
+}
+
+fn assigned_page(display_config: &AppDisplayConfig) -> Option<Page> {
+    display_config.page.as_deref().and_then(Page::from_name)
+}
+
+/// Shortens `text` to `max_chars`, marking the cut with "..".
+fn truncate_with_ellipsis(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let kept: String = text.chars().take(max_chars.saturating_sub(2)).collect();
+        format!("{}..", kept)
+    }
+}
+
+/// Draws `lines` horizontally and vertically centered on a cleared panel.
+fn draw_centered<P: PanelDriver>(
+    disp: &mut P,
+    lines: &[(&str, MonoTextStyle<BinaryColor>)],
+) -> Result<(), DisplayError> {
+    disp.clear(BinaryColor::Off)?;
+    disp.set_display_on(true)?;
+    let width = disp.bounding_box().size.width as i32;
+    let height: i32 = lines
+        .iter()
+        .map(|(_, style)| style.font.character_size.height as i32 + 1)
+        .sum();
+    let top = (disp.bounding_box().size.height as i32 - height) / 2;
+    let mut y = top;
+    for &(text, style) in lines {
+        let text_style = text_style(Alignment::Center, Baseline::Top);
+        draw_fitted(disp, text, style, text_style, Point::new(0, y), width, None)?;
+        y += style.font.character_size.height as i32 + 1;
+    }
+    disp.flush()
+}
+
+/// Draws `page` in the arrangement for the panel and sends it. `fan_icon`
+/// is the frame of the spinning fan glyph, `None` while the fan is off.
+fn draw_page<P: PanelDriver>(
+    disp: &mut P,
+    page: Page,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    fan_icon: Option<bool>,
+    offset: Point,
+) -> Result<(), DisplayError> {
+    disp.clear(BinaryColor::Off)?;
+
+    let size = disp.bounding_box().size;
+    let portrait = layout.portrait.unwrap_or(size.width < size.height);
+    match (page, portrait) {
+        (Page::Stats, false) => match &stats.summary {
+            Some(summary) => draw_summary_landscape(disp, stats, summary, layout, offset)?,
+            None => draw_landscape(disp, stats, layout, offset)?,
+        },
+        (Page::Stats, true) => draw_portrait(disp, stats, layout, offset)?,
+        (Page::System, false) => draw_system_landscape(disp, stats, layout, offset)?,
+        (Page::System, true) => draw_system_portrait(disp, stats, layout, offset)?,
+        (Page::Disk, false) => draw_disk_landscape(disp, stats, layout, offset)?,
+        (Page::Disk, true) => draw_disk_portrait(disp, stats, layout, offset)?,
+        (Page::Network, false) => draw_network_landscape(disp, stats, layout, offset)?,
+        (Page::Network, true) => draw_network_portrait(disp, stats, layout, offset)?,
+        (Page::Cores, _) => draw_cores(disp, stats, offset)?,
+        (Page::Power, false) => draw_power_landscape(disp, stats, layout, offset)?,
+        (Page::Power, true) => draw_power_portrait(disp, stats, layout, offset)?,
+        (Page::Qr, _) => draw_qr(disp, stats, layout, offset)?,
+        (Page::Clock, _) => draw_clock(disp, stats, layout, offset)?,
+        (Page::Cooldown, _) => draw_cooldown(disp, stats, layout, offset)?,
+    }
+
+    if let Some(frame) = fan_icon {
+        draw_fan_icon(disp, frame)?;
+    }
+    if !stats.throttle_status.is_empty() {
+        draw_throttle_warning(disp)?;
+    }
 
-        let y_offset = 7;
-        let display_width = 128;
-        let char_width: i32 = 8;
+    disp.flush()
+}
+
+/// 7x7 fan glyph with the blades upright and diagonal, one bit per pixel.
+const FAN_ICON: [[u8; 7]; 2] = [
+    [0x10, 0x10, 0x10, 0xFE, 0x10, 0x10, 0x10],
+    [0x82, 0x44, 0x28, 0x10, 0x28, 0x44, 0x82],
+];
+
+/// Fan glyph in the top-left corner, shown while the fan runs. Drawing the
+/// two frames alternately makes it spin.
+fn draw_fan_icon<D>(disp: &mut D, frame: bool) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    Rectangle::new(Point::zero(), Size::new(8, 8))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+        .draw(disp)?;
+    let raw = ImageRaw::<BinaryColor>::new(&FAN_ICON[frame as usize], 7);
+    Image::new(&raw, Point::zero()).draw(disp)?;
+    Ok(())
+}
+
+/// Inverted `!` in the top-right corner, shown while the Pi is throttled.
+fn draw_throttle_warning<D>(disp: &mut D) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let width = disp.bounding_box().size.width as i32;
+    let pos = Point::new(width - FONT_5X8.font.character_size.width as i32, 0);
+    Text::with_baseline("!", pos, FONT_5X8_INVERTED, Baseline::Top).draw(disp)?;
+    Ok(())
+}
+
+/// Draws a small label followed by a value, returning the end position.
+fn draw_labeled<D>(
+    disp: &mut D,
+    label: &str,
+    value: &str,
+    pos: Point,
+    layout: &LayoutOptions,
+) -> Result<Point, DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let next = Text::new(label, pos, FONT_5X8).draw(disp)?;
+    Text::new(value, next + Point::new(3, 0), layout.value_style).draw(disp)
+}
+
+/// Draws a stats page value, in `bold` when it is the emphasized one. The
+/// taller bold fonts only fit between the rows of 64px panels; 32px panels
+/// strike the regular font twice, one pixel apart, instead.
+fn draw_value<D>(
+    disp: &mut D,
+    text: &str,
+    pos: Point,
+    style: MonoTextStyle<'_, BinaryColor>,
+    bold: Option<MonoTextStyle<'_, BinaryColor>>,
+    tall: bool,
+) -> Result<Point, DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    match bold {
+        None => Text::new(text, pos, style).draw(disp),
+        Some(bold) if tall => Text::new(text, pos, bold).draw(disp),
+        Some(_) => {
+            Text::new(text, pos, style).draw(disp)?;
+            Text::new(text, pos + Point::new(1, 0), style).draw(disp)
+        }
+    }
+}
+
+fn draw_landscape<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let ip_address = &stats.ip_address;
+    let cpu_usage = &stats.cpu_usage_str;
+    let temp = &stats.cpu_temp_str;
+    let ram_usage = &stats.ram_usage_str;
+    let hostname = &stats.hostname;
+
+    let display_width = disp.bounding_box().size.width as i32;
+    let value_style = layout.value_style;
+    let char_width = value_style.font.character_size.width as i32;
+    // The CPU/RAM values end after four characters, the temperature and RPM
+    // columns leave room for the unit on the right.
+    let left_column = 2 + 4 * char_width;
+    let right_column = display_width - 21 - char_width;
 
-        let x_margin = Point::new(2, 0);
+    // 32px tall panels only fit three rows, so the hostname is dropped there
+    // unless a disabled field leaves a row free.
+    let tall = disp.bounding_box().size.height >= 64;
+    let (ip_y, row1_y, row2_y) = landscape_rows(disp, layout);
+    let rows = [ip_y, row1_y, row2_y];
+    let fields = layout.fields;
+
+    let x_margin = Point::new(2, 0);
+
+    let first_row = fields.ip as usize;
+    if fields.ip {
+        // IPv6 addresses do not fit the large font; switch to the small one
+        // and scroll or cut off whatever still overflows.
+        if ip_address.len() as i32 * char_width <= display_width {
+            let ip_width = ip_address.len() as i32 * char_width;
+            let ip_x = aligned_x(layout.ip_align, 0, display_width, ip_width);
+            let ip_pos = Point::new(ip_x, ip_y) + offset;
+            Text::new(ip_address, ip_pos, value_style).draw(disp)?;
+        } else {
+            let ip_pos = Point::new(0, ip_y) + offset;
+            draw_fitted(
+                disp,
+                ip_address,
+                FONT_5X8,
+                text_style(layout.ip_align, Baseline::Alphabetic),
+                ip_pos,
+                display_width,
+                layout.scroll,
+            )?;
+        }
+    }
 
-        disp.clear(BinaryColor::Off)?;
+    // Each column moves up on its own to fill the rows of disabled fields.
+    let mut left_rows = first_row..rows.len();
+    let cpu_row = fields.cpu.then(|| left_rows.next()).flatten();
+    let ram_row = fields.ram.then(|| left_rows.next()).flatten();
+    let mut right_rows = first_row..rows.len();
+    let temp_row = fields.temp.then(|| right_rows.next()).flatten();
+    let rpm_row = (fields.rpm && stats.fan_rpm.is_some())
+        .then(|| right_rows.next())
+        .flatten();
 
-        let ip_width = ip_address.len() as i32 * char_width;
-        let ip_x_position = (display_width - ip_width) / 2;
-        let ip_pos = Point::new(ip_x_position, y_offset) + offset;
-        Text::new(ip_address, ip_pos, PCSENIOR8_STYLE).draw(disp)?;
+    // The bold font has the width of the regular one, so the emphasized
+    // value keeps its column.
+    let bold = |field| (layout.emphasized == Some(field)).then_some(layout.value_style_bold);
 
-        let cpu_width = cpu_usage.len() as i32 * char_width;
-        let cpu_pos = Point::new(34 - cpu_width, 12 + y_offset) + offset;
-        let next = Text::new(&cpu_usage, cpu_pos, PCSENIOR8_STYLE).draw(disp)?;
+    // Values are aligned within their four character wide columns.
+    let column_width = 4 * char_width;
+    let value_x = |value: &str, left| {
+        aligned_x(
+            layout.value_align,
+            left,
+            column_width,
+            value.len() as i32 * char_width,
+        )
+    };
+    if let Some(row) = cpu_row {
+        let cpu_pos = Point::new(value_x(cpu_usage, 2), rows[row]) + offset;
+        let next = draw_value(
+            disp,
+            cpu_usage,
+            cpu_pos,
+            value_style,
+            bold(Field::Cpu),
+            tall,
+        )?;
         let next = Text::new("%", next, FONT_6X12).draw(disp)?;
         Text::new("CPU", next + x_margin, FONT_5X8).draw(disp)?;
+    }
 
-        let ram_width = ram_usage.len() as i32 * char_width;
-        let ram_pos = Point::new(34 - ram_width, 23 + y_offset) + offset;
-        let next = Text::new(&ram_usage, ram_pos, PCSENIOR8_STYLE).draw(disp)?;
-        let next = Text::new("%", next, FONT_6X12).draw(disp)?;
+    if let Some(row) = ram_row {
+        let next = if stats.ram_unit == "%" {
+            let ram_pos = Point::new(value_x(ram_usage, 2), rows[row]) + offset;
+            let next = draw_value(
+                disp,
+                ram_usage,
+                ram_pos,
+                value_style,
+                bold(Field::Ram),
+                tall,
+            )?;
+            Text::new("%", next, FONT_6X12).draw(disp)?
+        } else {
+            // Used/total is wider than the column; the small font leaves room
+            // for the RPM on the right.
+            let ram_pos = Point::new(2, rows[row]) + offset;
+            let ram_bold = bold(Field::Ram).map(|_| FONT_6X13_BOLD);
+            let next = draw_value(disp, ram_usage, ram_pos, FONT_6X10, ram_bold, tall)?;
+            Text::new(stats.ram_unit, next, FONT_5X8).draw(disp)?
+        };
         Text::new("RAM", next + x_margin, FONT_5X8).draw(disp)?;
+    }
 
-        let temp_width = temp.len() as i32 * char_width;
-        let temp_pos = Point::new(99 - temp_width, 12 + y_offset) + offset;
-        let next = Text::new(&temp, temp_pos, PCSENIOR8_STYLE).draw(disp)?;
+    if layout.show_bars {
+        // The bars sit in the gap above each row: the single free line on
+        // 32px panels, a framed 3px bar where there is room for it. A row
+        // moved up to the top of the panel has no room for a bar.
+        let bar_height = if tall { 3 } else { 1 };
+        let bar_y = |row: usize, field| {
+            if tall {
+                let style = bold(field).unwrap_or(value_style);
+                let above_text = style.font.baseline as i32 + 4;
+                Some(rows[row] - above_text).filter(|&y| y >= 0)
+            } else {
+                row.checked_sub(1).map(|above| rows[above] + 3)
+            }
+        };
+        let bar_width = (left_column + BAR_LABEL_WIDTH) as u32;
+        if let Some(y) = cpu_row.and_then(|row| bar_y(row, Field::Cpu)) {
+            let cpu_bar = Point::new(2, y) + offset;
+            draw_bar(
+                disp,
+                cpu_bar,
+                bar_width,
+                bar_height,
+                stats.cpu_usage_shown as f64,
+            )?;
+        }
+        if let Some(y) = ram_row.and_then(|row| bar_y(row, Field::Ram)) {
+            let ram_bar = Point::new(2, y) + offset;
+            draw_bar(disp, ram_bar, bar_width, bar_height, stats.ram_usage)?;
+        }
+    }
+
+    let right_left = right_column - column_width;
+    if let Some(row) = temp_row {
+        let temp_pos = Point::new(value_x(temp, right_left), rows[row]) + offset;
+        let temp_bold = bold(Field::Temp);
+        let next = draw_value(disp, temp, temp_pos, value_style, temp_bold, tall)?;
         let next = Text::new("°", next + Point::new(0, 3), PROFONT12).draw(disp)?;
-        Text::new("C", next - Point::new(0, 2), PCSENIOR8_STYLE).draw(disp)?;
+        let unit_pos = next - Point::new(0, 2);
+        draw_value(
+            disp,
+            layout.temp_unit,
+            unit_pos,
+            value_style,
+            temp_bold,
+            tall,
+        )?;
+    }
+
+    if let (Some(row), Some(rpm)) = (rpm_row, stats.fan_rpm) {
+        let rpm = rpm.to_string();
+        let rpm_pos = Point::new(value_x(&rpm, right_left), rows[row]) + offset;
+        let next = draw_value(disp, &rpm, rpm_pos, value_style, bold(Field::Rpm), tall)?;
+        Text::new("RPM", next + x_margin, FONT_5X8).draw(disp)?;
+    }
+
+    let used_rows = left_rows.start.max(right_rows.start);
+    if !tall && fields.hostname && used_rows < rows.len() {
+        let host_pos = Point::new(0, rows[used_rows]) + offset;
+        draw_fitted(
+            disp,
+            hostname,
+            FONT_5X8,
+            text_style(layout.hostname_align, Baseline::Alphabetic),
+            host_pos,
+            display_width,
+            layout.scroll,
+        )?;
+    }
+
+    if tall && stats.temp_history.len() >= 2 {
+        let area = Rectangle::new(
+            Point::new(0, 55) + offset,
+            Size::new(display_width as u32, 8),
+        );
+        draw_sparkline(disp, &stats.temp_history, area)?;
+    } else if tall && fields.hostname {
+        let host_pos = Point::new(0, 60) + offset;
+        draw_fitted(
+            disp,
+            hostname,
+            FONT_5X8,
+            text_style(layout.hostname_align, Baseline::Alphabetic),
+            host_pos,
+            display_width,
+            layout.scroll,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Smallest temperature range the sparkline is scaled to, so sensor noise
+/// does not fill its whole height.
+const SPARKLINE_MIN_RANGE: f32 = 2.0;
+
+/// Line through `samples` spread across `area`, lowest value at the bottom.
+fn draw_sparkline<D>(disp: &mut D, samples: &[f32], area: Rectangle) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let min = samples.iter().copied().fold(f32::MAX, f32::min);
+    let max = samples.iter().copied().fold(f32::MIN, f32::max);
+    let range = (max - min).max(SPARKLINE_MIN_RANGE);
+    let low = (min + max - range) / 2.0;
+    let last_x = area.size.width.saturating_sub(1) as f32;
+    let last_y = area.size.height.saturating_sub(1) as f32;
+    let step = last_x / (samples.len() - 1) as f32;
+    let point = |i: usize, sample: f32| {
+        let x = (i as f32 * step).round() as i32;
+        let y = (last_y - (sample - low) / range * last_y).round() as i32;
+        area.top_left + Point::new(x, y)
+    };
+    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    for (i, pair) in samples.windows(2).enumerate() {
+        Line::new(point(i, pair[0]), point(i + 1, pair[1]))
+            .into_styled(style)
+            .draw(disp)?;
+    }
+    Ok(())
+}
+
+/// Width of the `%CPU` after the CPU/RAM values, which the usage bars span
+/// together with the value.
+const BAR_LABEL_WIDTH: i32 = 22;
+
+/// Horizontal bar filled from the left by `percent`. Bars taller than two
+/// pixels get a frame so an empty bar is still visible.
+fn draw_bar<D>(
+    disp: &mut D,
+    top_left: Point,
+    width: u32,
+    height: u32,
+    percent: f64,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+    let percent = percent.clamp(0.0, 100.0);
+    if height > 2 {
+        Rectangle::new(top_left, Size::new(width, height))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(disp)?;
+    }
+    let filled = (width as f64 * percent / 100.0).round() as u32;
+    if filled > 0 {
+        Rectangle::new(top_left, Size::new(filled, height))
+            .into_styled(fill)
+            .draw(disp)?;
+    }
+    Ok(())
+}
+
+/// Stats page with `summary_fields`: the IP in the largest font that fits
+/// and the values on one line below it.
+fn draw_summary_landscape<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    summary: &str,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let size = disp.bounding_box().size;
+    let display_width = size.width as i32;
+    let tall = size.height >= 64;
+    let fields = layout.fields;
+    let largest_fitting = |text: &str, styles: [MonoTextStyle<'static, BinaryColor>; 3]| {
+        styles
+            .into_iter()
+            .find(|style| {
+                text.chars().count() as u32 * style.font.character_size.width <= size.width
+            })
+            .unwrap_or(styles[2])
+    };
+    let ip_style = largest_fitting(&stats.ip_address, [FONT_9X15, FONT_6X10, FONT_5X8]);
+    let summary_style = largest_fitting(summary, [layout.value_style, FONT_6X10, FONT_5X8]);
+
+    // The IP and summary are centered together above the hostname or
+    // sparkline of tall panels.
+    let gap = 4;
+    let ip_height = if fields.ip {
+        ip_style.font.character_size.height as i32 + gap
+    } else {
+        0
+    };
+    let summary_height = summary_style.font.character_size.height as i32;
+    let area_height = if tall { 52 } else { size.height as i32 };
+    let top = ((area_height - ip_height - summary_height) / 2).max(0);
+
+    if fields.ip {
+        draw_fitted(
+            disp,
+            &stats.ip_address,
+            ip_style,
+            text_style(layout.ip_align, Baseline::Top),
+            Point::new(0, top) + offset,
+            display_width,
+            layout.scroll,
+        )?;
+    }
+    draw_fitted(
+        disp,
+        summary,
+        summary_style,
+        text_style(Alignment::Center, Baseline::Top),
+        Point::new(0, top + ip_height) + offset,
+        display_width,
+        layout.scroll,
+    )?;
+
+    if tall && stats.temp_history.len() >= 2 {
+        let area = Rectangle::new(
+            Point::new(0, 55) + offset,
+            Size::new(display_width as u32, 8),
+        );
+        draw_sparkline(disp, &stats.temp_history, area)?;
+    } else if tall && fields.hostname {
+        let host_pos = Point::new(0, 60) + offset;
+        draw_fitted(
+            disp,
+            &stats.hostname,
+            FONT_5X8,
+            text_style(layout.hostname_align, Baseline::Alphabetic),
+            host_pos,
+            display_width,
+            layout.scroll,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Stacked layout for displays rotated by 90/270 degrees, where the canvas is
+/// only as wide as the panel is tall. Every line is centered horizontally.
+fn draw_portrait<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let width = disp.bounding_box().size.width as i32;
+    let max_chars = (width / FONT_4X6.font.character_size.width as i32) as usize;
+
+    let (ip_first, ip_second) = split_address(&stats.ip_address, max_chars);
+
+    let cpu_usage = format!("{}%", stats.cpu_usage_str);
+    let temp = format!("{}{}", stats.cpu_temp_str, layout.temp_unit);
+    let ram_usage = format!("{}{}", stats.ram_usage_str, stats.ram_unit);
+    let fields = layout.fields;
+    let rpm = stats
+        .fan_rpm
+        .filter(|_| fields.rpm)
+        .map(|rpm| rpm.to_string());
+    let mut lines = Vec::new();
+    if fields.ip {
+        lines.extend([(ip_first, FONT_4X6), (ip_second, FONT_4X6)]);
+    }
+    // The emphasized value is taller; leaving out the gap above its label
+    // keeps the page from growing.
+    let mut add_field = |label, value, field| {
+        let emphasized = layout.emphasized == Some(field);
+        if !lines.is_empty() && !emphasized {
+            lines.push(("", FONT_4X6));
+        }
+        let value_style = if emphasized { FONT_6X13_BOLD } else { FONT_5X8 };
+        lines.extend([(label, FONT_5X8), (value, value_style)]);
+    };
+    if let Some(summary) = &stats.summary {
+        if !lines.is_empty() {
+            lines.push(("", FONT_4X6));
+        }
+        lines.push((summary.as_str(), FONT_4X6));
+    } else {
+        if fields.cpu {
+            add_field("CPU", cpu_usage.as_str(), Field::Cpu);
+        }
+        if fields.temp {
+            add_field("TEMP", temp.as_str(), Field::Temp);
+        }
+        if fields.ram {
+            add_field("RAM", ram_usage.as_str(), Field::Ram);
+        }
+        if let Some(rpm) = &rpm {
+            add_field("RPM", rpm.as_str(), Field::Rpm);
+        }
+    }
+    if fields.hostname {
+        if !lines.is_empty() {
+            lines.push(("", FONT_4X6));
+        }
+        lines.push((stats.hostname.as_str(), FONT_4X6));
+    }
+
+    draw_stacked(disp, &lines, layout.scroll, offset)
+}
+
+fn draw_system_landscape<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let (row0, row1, row2) = landscape_rows(disp, layout);
+    let up_pos = Point::new(2, row0) + offset;
+    let mut uptime = stats.uptime_str.as_str();
+    if stats.cpu_freq.is_some() {
+        // Right-aligned next to the uptime, which loses its minutes when
+        // both do not fit.
+        let char_width = layout.value_style.font.character_size.width as i32;
+        let freq_x = disp.bounding_box().size.width as i32
+            - 2
+            - stats.cpu_freq_str.len() as i32 * char_width;
+        let label_width = 2 * FONT_5X8.font.character_size.width as i32 + 3;
+        if 2 + label_width + (uptime.len() as i32 + 1) * char_width > freq_x {
+            uptime = uptime.rsplit_once(' ').map_or(uptime, |(rest, _)| rest);
+        }
+        let freq_pos = Point::new(freq_x, row0) + offset;
+        Text::new(&stats.cpu_freq_str, freq_pos, layout.value_style).draw(disp)?;
+    }
+    draw_labeled(disp, "UP", uptime, up_pos, layout)?;
+    let load_pos = Point::new(2, row1) + offset;
+    draw_labeled(disp, "LOAD", &stats.load_avg_str, load_pos, layout)?;
+    let swap_style = if stats.swap_warning {
+        layout.value_style_inverted
+    } else {
+        layout.value_style
+    };
+    let next = Text::new("SWAP", Point::new(2, row2) + offset, FONT_5X8).draw(disp)?;
+    Text::new(&stats.swap_usage_str, next + Point::new(3, 0), swap_style).draw(disp)?;
+    if stats.gpu_temp.is_some() {
+        // Right-aligned next to the swap usage.
+        let char_width = layout.value_style.font.character_size.width as i32;
+        let gpu_x = disp.bounding_box().size.width as i32
+            - 2
+            - stats.gpu_temp_str.len() as i32 * char_width;
+        let gpu_pos = Point::new(gpu_x, row2) + offset;
+        Text::new(&stats.gpu_temp_str, gpu_pos, layout.value_style).draw(disp)?;
+        Text::with_alignment(
+            "GPU",
+            gpu_pos - Point::new(3, 0),
+            FONT_5X8,
+            Alignment::Right,
+        )
+        .draw(disp)?;
+    }
+    Ok(())
+}
+
+fn draw_system_portrait<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let mut lines = vec![("UP", FONT_5X8)];
+    lines.extend(stats.uptime_str.split(' ').map(|part| (part, FONT_4X6)));
+    lines.push(("", FONT_4X6));
+    if stats.cpu_freq.is_some() {
+        lines.push(("FREQ", FONT_5X8));
+        lines.push((stats.cpu_freq_str.as_str(), FONT_4X6));
+        lines.push(("", FONT_4X6));
+    }
+    lines.push(("LOAD", FONT_5X8));
+    lines.extend(stats.load_avg_str.split(' ').map(|part| (part, FONT_4X6)));
+    lines.push(("", FONT_4X6));
+    lines.push(("SWAP", FONT_5X8));
+    let swap_style = if stats.swap_warning {
+        FONT_5X8_INVERTED
+    } else {
+        FONT_5X8
+    };
+    lines.push((stats.swap_usage_str.as_str(), swap_style));
+    let gpu_temp = format!("{}{}", stats.gpu_temp_str, layout.temp_unit);
+    if stats.gpu_temp.is_some() {
+        lines.push(("", FONT_4X6));
+        lines.push(("GPU", FONT_5X8));
+        lines.push((gpu_temp.as_str(), FONT_5X8));
+    }
+    draw_stacked(disp, &lines, layout.scroll, offset)
+}
+
+fn draw_disk_landscape<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let (row0, row1, _) = landscape_rows(disp, layout);
+    let usage = format!("{}%", stats.disk_usage_str);
+    let next = Text::new("DISK", Point::new(2, row0) + offset, FONT_5X8).draw(disp)?;
+    let usage_style = if stats.disk_warning {
+        layout.value_style_inverted
+    } else {
+        layout.value_style
+    };
+    Text::new(&usage, next + Point::new(3, 0), usage_style).draw(disp)?;
+    let space = format_disk_space(stats.disk_used, stats.disk_total);
+    Text::new(&space, Point::new(2, row1) + offset, layout.value_style).draw(disp)?;
+    Ok(())
+}
+
+fn draw_disk_portrait<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let usage = format!("{}%", stats.disk_usage_str);
+    let space = format_disk_space(stats.disk_used, stats.disk_total);
+    let usage_style = if stats.disk_warning {
+        FONT_5X8_INVERTED
+    } else {
+        FONT_5X8
+    };
+    let lines = [
+        ("DISK", FONT_5X8),
+        (usage.as_str(), usage_style),
+        ("", FONT_4X6),
+        (space.as_str(), FONT_4X6),
+    ];
+    draw_stacked(disp, &lines, layout.scroll, offset)
+}
+
+/// Power draw from the INA219, or dashes while it cannot be read.
+fn power_values(stats: &SystemStats) -> [String; 3] {
+    match (stats.power_watts, stats.power_voltage, stats.power_current) {
+        (Some(watts), Some(voltage), Some(current)) => [
+            format!("{:.2}W", watts),
+            format!("{:.2}V", voltage),
+            format!("{:.2}A", current),
+        ],
+        _ => ["--".to_string(), "--".to_string(), "--".to_string()],
+    }
+}
+
+/// The time in the largest font it fits in, centered on the panel.
+fn draw_clock<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let size = disp.bounding_box().size;
+    let text = stats.time.as_str();
+    let style = [FONT_9X15, FONT_6X10, FONT_4X6]
+        .into_iter()
+        .find(|style| text.len() as u32 * style.font.character_size.width <= size.width)
+        .unwrap_or(FONT_4X6);
+    let y = (size.height as i32 - style.font.character_size.height as i32) / 2;
+    draw_fitted(
+        disp,
+        text,
+        style,
+        text_style(Alignment::Center, Baseline::Top),
+        Point::new(0, y) + offset,
+        size.width as i32,
+        layout.scroll,
+    )
+}
+
+/// Thermal page: the CPU temperature in the largest font that fits, the fan
+/// speed and the temperature sparkline below.
+fn draw_cooldown<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let size = disp.bounding_box().size;
+    let width = size.width as i32;
+    let temp = format!("{}{}", stats.cpu_temp_str, layout.temp_unit);
+    let fan = stats
+        .fan_rpm
+        .map_or_else(|| "FAN ON".to_string(), |rpm| format!("{}RPM", rpm));
+    let text_width = |text: &str, style: &MonoTextStyle<BinaryColor>| {
+        text.len() as i32 * style.font.character_size.width as i32
+    };
+    let temp_style = [FONT_9X15, FONT_6X10, FONT_5X8]
+        .into_iter()
+        .find(|style| text_width(&temp, style) <= width)
+        .unwrap_or(FONT_4X6);
+    let fan_style = if text_width(&fan, &FONT_5X8) <= width {
+        FONT_5X8
+    } else {
+        FONT_4X6
+    };
+    let temp_height = temp_style.font.character_size.height as i32;
+
+    // Temperature and fan speed share the top row where the panel is wide
+    // enough, otherwise the fan speed goes below the temperature.
+    let same_row = text_width(&temp, &temp_style) + text_width(&fan, &fan_style) + 6 <= width;
+    let fan_height = fan_style.font.character_size.height as i32;
+    let (temp_pos, temp_align, fan_pos, fan_align, bottom) = if same_row {
+        let fan_y = (temp_height - fan_height) / 2 + 1;
+        (
+            Point::new(2, 1),
+            Alignment::Left,
+            Point::new(width - 2, fan_y),
+            Alignment::Right,
+            temp_height + 3,
+        )
+    } else {
+        let fan_y = temp_height + 2;
+        (
+            Point::new(width / 2, 1),
+            Alignment::Center,
+            Point::new(width / 2, fan_y),
+            Alignment::Center,
+            fan_y + fan_height + 2,
+        )
+    };
+    Text::with_text_style(
+        &temp,
+        temp_pos + offset,
+        temp_style,
+        text_style(temp_align, Baseline::Top),
+    )
+    .draw(disp)?;
+    Text::with_text_style(
+        &fan,
+        fan_pos + offset,
+        fan_style,
+        text_style(fan_align, Baseline::Top),
+    )
+    .draw(disp)?;
+
+    let height = size.height as i32 - bottom;
+    if stats.temp_history.len() >= 2 && height >= 4 {
+        let area = Rectangle::new(
+            Point::new(0, bottom) + offset,
+            Size::new(size.width, height as u32),
+        );
+        draw_sparkline(disp, &stats.temp_history, area)?;
+    }
+    Ok(())
+}
+
+/// QR code of `stats.qr_text`, drawn dark on a lit square so phone cameras
+/// read it, with the IP address and hostname next to or below it.
+fn draw_qr<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let size = disp.bounding_box().size;
+    let lines = [
+        (stats.ip_address.as_str(), FONT_4X6),
+        (stats.hostname.as_str(), FONT_4X6),
+    ];
+    let qr = stats
+        .qr_text
+        .as_deref()
+        .and_then(|text| QrCode::encode_text(text, QrCodeEcc::Low).ok());
+    let Some(qr) = qr else {
+        return draw_stacked(disp, &[("NO QR", FONT_5X8)], None, offset);
+    };
+
+    // One module of quiet zone on each side; the lit panel border does the
+    // rest.
+    let modules = qr.size() + 2;
+    let module_px = (size.width.min(size.height) as i32 / modules).max(1);
+    let side = modules * module_px;
+    let portrait = size.width < size.height;
+    let qr_pos = if portrait {
+        Point::new((size.width as i32 - side) / 2, 0)
+    } else {
+        Point::new(0, (size.height as i32 - side) / 2)
+    } + offset;
+    Rectangle::new(qr_pos, Size::new(side as u32, side as u32))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(disp)?;
+    for y in 0..qr.size() {
+        for x in 0..qr.size() {
+            if qr.get_module(x, y) {
+                let module_pos = qr_pos + Point::new(x + 1, y + 1) * module_px;
+                Rectangle::new(module_pos, Size::new(module_px as u32, module_px as u32))
+                    .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+                    .draw(disp)?;
+            }
+        }
+    }
+
+    let text_style = text_style(Alignment::Center, Baseline::Top);
+    if portrait {
+        let mut y = side + 2;
+        for (text, style) in lines {
+            let pos = Point::new(0, y) + offset;
+            draw_fitted(
+                disp,
+                text,
+                style,
+                text_style,
+                pos,
+                size.width as i32,
+                layout.scroll,
+            )?;
+            y += style.font.character_size.height as i32 + 1;
+        }
+    } else {
+        let text_width = size.width as i32 - side - 2;
+        let line_height = FONT_4X6.font.character_size.height as i32 + 1;
+        let mut y = (size.height as i32 - 2 * line_height) / 2;
+        for (text, style) in lines {
+            let pos = Point::new(side + 2, y) + offset;
+            draw_fitted(
+                disp,
+                text,
+                style,
+                text_style,
+                pos,
+                text_width,
+                layout.scroll,
+            )?;
+            y += line_height;
+        }
+    }
+    Ok(())
+}
+
+fn draw_power_landscape<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let (row0, row1, row2) = landscape_rows(disp, layout);
+    let [watts, voltage, current] = power_values(stats);
+    draw_labeled(disp, "PWR", &watts, Point::new(2, row0) + offset, layout)?;
+    draw_labeled(disp, "VOLT", &voltage, Point::new(2, row1) + offset, layout)?;
+    draw_labeled(disp, "CURR", &current, Point::new(2, row2) + offset, layout)?;
+    Ok(())
+}
+
+fn draw_power_portrait<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let [watts, voltage, current] = power_values(stats);
+    let lines = [
+        ("PWR", FONT_5X8),
+        (watts.as_str(), FONT_5X8),
+        ("", FONT_4X6),
+        (voltage.as_str(), FONT_4X6),
+        (current.as_str(), FONT_4X6),
+    ];
+    draw_stacked(disp, &lines, layout.scroll, offset)
+}
+
+fn draw_network_landscape<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let (row0, row1, row2) = landscape_rows(disp, layout);
+    draw_labeled(
+        disp,
+        "RX",
+        &stats.net_rx_str,
+        Point::new(2, row0) + offset,
+        layout,
+    )?;
+    draw_labeled(
+        disp,
+        "TX",
+        &stats.net_tx_str,
+        Point::new(2, row1) + offset,
+        layout,
+    )?;
+    let interface = format!("NET {}", stats.net_interface);
+    let next = Text::new(&interface, Point::new(2, row2) + offset, FONT_5X8).draw(disp)?;
+    // Right-aligned next to the interface, in the small font when the
+    // address is too long for both to fit.
+    let width = disp.bounding_box().size.width as i32;
+    let gateway = format!("GW {}", stats.gateway.as_deref().unwrap_or("-"));
+    let gateway_width = gateway.len() as i32 * FONT_5X8.font.character_size.width as i32;
+    let gateway_style = if next.x + 3 + gateway_width <= width - 2 + offset.x {
+        FONT_5X8
+    } else {
+        FONT_4X6
+    };
+    let gateway_pos = Point::new(width - 2, row2) + offset;
+    Text::with_alignment(&gateway, gateway_pos, gateway_style, Alignment::Right).draw(disp)?;
+    // Tall panels have room for the local address below.
+    if disp.bounding_box().size.height >= 64 {
+        let ip = format!("IP {}", stats.ip_address);
+        let ip_pos = Point::new(2, row2 + 14) + offset;
+        draw_fitted(
+            disp,
+            &ip,
+            FONT_5X8,
+            TextStyle::with_alignment(Alignment::Left),
+            ip_pos,
+            width - 4,
+            layout.scroll,
+        )?;
+    }
+    Ok(())
+}
 
-        let host_width = hostname.len() as i32 * char_width;
-        let host_x = (display_width - host_width) / 2;
-        let host_pos = Point::new(host_x, 33) + offset;
-        Text::new(hostname, host_pos, FONT_5X8).draw(disp)?;
+fn draw_network_portrait<D>(
+    disp: &mut D,
+    stats: &SystemStats,
+    layout: &LayoutOptions,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let max_chars = (disp.bounding_box().size.width / FONT_4X6.font.character_size.width) as usize;
+    let (gateway_first, gateway_second) =
+        split_address(stats.gateway.as_deref().unwrap_or("-"), max_chars);
+    let lines = [
+        ("NET", FONT_5X8),
+        (stats.net_interface.as_str(), FONT_4X6),
+        ("", FONT_4X6),
+        ("RX", FONT_5X8),
+        (stats.net_rx_str.as_str(), FONT_4X6),
+        ("", FONT_4X6),
+        ("TX", FONT_5X8),
+        (stats.net_tx_str.as_str(), FONT_4X6),
+        ("", FONT_4X6),
+        ("GW", FONT_5X8),
+        (gateway_first, FONT_4X6),
+        (gateway_second, FONT_4X6),
+    ];
+    draw_stacked(disp, &lines, layout.scroll, offset)
+}
+
+/// One usage bar per CPU core, labeled with the core index. The percentage
+/// is added on the right when the panel is wide enough.
+fn draw_cores<D>(disp: &mut D, stats: &SystemStats, offset: Point) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let cores = &stats.cpu_per_core;
+    if cores.is_empty() {
+        return Ok(());
+    }
+    let size = disp.bounding_box().size;
+    let (width, height) = (size.width as i32, size.height as i32);
+    let char_width = FONT_4X6.font.character_size.width as i32;
+    let row_height = (height / cores.len() as i32).clamp(2, 8);
+    let top = (height - row_height * cores.len() as i32).max(0) / 2;
+
+    let label_width = char_width * 2;
+    let percent_width = if width >= 64 { char_width * 5 } else { 0 };
+    let bar_width = (width - label_width - percent_width - 2).max(1) as u32;
+    let bar_height = (row_height - 2).max(1) as u32;
+    let right = TextStyleBuilder::new()
+        .alignment(Alignment::Right)
+        .baseline(Baseline::Top)
+        .build();
+
+    for (i, usage) in cores.iter().enumerate() {
+        let y = top + i as i32 * row_height;
+        let label = i.to_string();
+        Text::with_baseline(&label, Point::new(0, y) + offset, FONT_4X6, Baseline::Top)
+            .draw(disp)?;
+        let bar_pos = Point::new(label_width, y) + offset;
+        draw_bar(disp, bar_pos, bar_width, bar_height, *usage as f64)?;
+        if percent_width > 0 {
+            let percent = format!("{:.0}%", usage);
+            let pos = Point::new(width - 1, y) + offset;
+            Text::with_text_style(&percent, pos, FONT_4X6, right).draw(disp)?;
+        }
+    }
+    Ok(())
+}
+
+/// Breaks an address longer than `max_chars` after the last separator that
+/// still fits on the first line; long IPv6 addresses scroll or are cut off
+/// on the second.
+fn split_address(address: &str, max_chars: usize) -> (&str, &str) {
+    match address[..max_chars.min(address.len())].rfind(['.', ':']) {
+        Some(i) if address.len() > max_chars => address.split_at(i + 1),
+        _ => (address, ""),
+    }
+}
+
+/// Formats used/total bytes as e.g. `12.3/29.1G`.
+fn format_disk_space(used: u64, total: u64) -> String {
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+    format!("{:.1}/{:.1}G", used as f64 / GIB, total as f64 / GIB)
+}
+
+/// Draws each line centered horizontally, one below the other. Lines wider
+/// than the display are handled by [`draw_fitted`].
+fn draw_stacked<D>(
+    disp: &mut D,
+    lines: &[(&str, MonoTextStyle<'_, BinaryColor>)],
+    scroll: Option<u32>,
+    offset: Point,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let width = disp.bounding_box().size.width as i32;
+    let mut y = 0;
+    for &(text, style) in lines {
+        let pos = Point::new(0, y) + offset;
+        let text_style = text_style(Alignment::Center, Baseline::Top);
+        draw_fitted(disp, text, style, text_style, pos, width, scroll)?;
+        y += style.font.character_size.height as i32 + 1;
+    }
+    Ok(())
+}
+
+/// Gap between the end of marquee text and its next repetition.
+const MARQUEE_GAP: i32 = 16;
+
+fn text_style(alignment: Alignment, baseline: Baseline) -> TextStyle {
+    TextStyleBuilder::new()
+        .alignment(alignment)
+        .baseline(baseline)
+        .build()
+}
+
+/// X coordinate of the anchor for text aligned within the `width` pixels
+/// right of `left`.
+fn anchor_x(alignment: Alignment, left: i32, width: i32) -> i32 {
+    match alignment {
+        Alignment::Left => left,
+        Alignment::Center => left + width / 2,
+        Alignment::Right => left + width - 1,
+    }
+}
+
+/// Left edge of `text_width` pixels of text aligned within the `width`
+/// pixels right of `left`.
+fn aligned_x(alignment: Alignment, left: i32, width: i32, text_width: i32) -> i32 {
+    match alignment {
+        Alignment::Left => left,
+        Alignment::Center => left + (width - text_width) / 2,
+        Alignment::Right => left + width - text_width,
+    }
+}
+
+/// Draws `text` aligned in the `width` pixels right of `pos`. Text that does
+/// not fit scrolls left by `scroll` pixels and wraps around, or is cut off
+/// when scrolling is off.
+fn draw_fitted<D>(
+    disp: &mut D,
+    text: &str,
+    style: MonoTextStyle<'_, BinaryColor>,
+    text_style: TextStyle,
+    pos: Point,
+    width: i32,
+    scroll: Option<u32>,
+) -> Result<(), DisplayError>
+where
+    D: DrawTarget<Color = BinaryColor, Error = DisplayError>,
+{
+    let char_width = style.font.character_size.width as i32;
+    let text_width = text.chars().count() as i32 * char_width;
+    let anchor = Point::new(anchor_x(text_style.alignment, pos.x, width), pos.y);
+
+    if text_width <= width {
+        Text::with_text_style(text, anchor, style, text_style).draw(disp)?;
+        return Ok(());
+    }
+    match scroll {
+        Some(scroll) => {
+            let cycle = text_width + MARQUEE_GAP;
+            let shift = (scroll % cycle as u32) as i32;
+            for x in [-shift, cycle - shift] {
+                Text::with_baseline(text, pos + Point::new(x, 0), style, text_style.baseline)
+                    .draw(disp)?;
+            }
+        }
+        None => {
+            let max_chars = (width / char_width) as usize;
+            let truncated = truncate_with_ellipsis(text, max_chars);
+            Text::with_text_style(&truncated, anchor, style, text_style).draw(disp)?;
+        }
+    }
+    Ok(())
+}
+
+fn open_display(
+    display_config: &AppDisplayConfig,
+    address: u8,
+    frames_dir: Option<&Path>,
+) -> Result<Display, Box<dyn std::error::Error>> {
+    if let Some(frames_dir) = frames_dir {
+        let (width, height) = if display_config.size == "128x64" {
+            (128, 64)
+        } else {
+            (128, 32)
+        };
+        let size = if matches!(display_config.rotation, 90 | 270) {
+            Size::new(height, width)
+        } else {
+            Size::new(width, height)
+        };
+        let panel = SimulatedPanel::new(frames_dir, address, size, display_config.invert_colors)?;
+        return Ok(Display::Simulated(panel));
+    }
+    let i2c = I2cdev::new(&display_config.i2c_bus)
+        .map_err(|e| format!("Failed to open I2C bus {}: {}", display_config.i2c_bus, e))?;
+    initialize_display(i2c, address, display_config)
+}
 
-        disp.flush()
+fn map_alignment_value(value: &str) -> Alignment {
+    match value {
+        "left" => Alignment::Left,
+        "right" => Alignment::Right,
+        _ => Alignment::Center,
     }
 }
 
@@ -102,26 +1641,300 @@ fn map_brightness_value(value: u8) -> Brightness {
     }
 }
 
+fn map_rotation_value(value: u16) -> DisplayRotation {
+    match value {
+        0 => DisplayRotation::Rotate0,
+        90 => DisplayRotation::Rotate90,
+        180 => DisplayRotation::Rotate180,
+        270 => DisplayRotation::Rotate270,
+        _ => {
+            warn!(
+                "Invalid rotation value {} encountered, defaulting to 0 degrees",
+                value
+            );
+            DisplayRotation::Rotate0
+        }
+    }
+}
+
 fn initialize_display(
     i2c: I2cdev,
+    address: u8,
     display_config: &AppDisplayConfig,
 ) -> Result<Display, Box<dyn std::error::Error>> {
-    let interface = I2CDisplayInterface::new(i2c);
-    let mut disp = Ssd1306::new(interface, DisplaySize128x32, DisplayRotation::Rotate0)
-        .into_buffered_graphics_mode();
+    let interface = I2CDisplayInterface::new_custom_address(i2c, address);
+    let rotation = map_rotation_value(display_config.rotation);
+    let brightness = map_brightness_value(display_config.brightness);
+
+    let display = match display_config.size.as_str() {
+        "128x64" => Display::Size128x64(init_panel(
+            interface,
+            DisplaySize128x64,
+            rotation,
+            brightness,
+            display_config.invert_colors,
+        )?),
+        size => {
+            if size != "128x32" {
+                warn!("Invalid display size {:?}, defaulting to 128x32", size);
+            }
+            Display::Size128x32(init_panel(
+                interface,
+                DisplaySize128x32,
+                rotation,
+                brightness,
+                display_config.invert_colors,
+            )?)
+        }
+    };
+
+    info!(
+        "Display 0x{:02X} initialized with size: {}, brightness: {:?}, rotation: {:?}, inverted: {}",
+        address, display_config.size, brightness, rotation, display_config.invert_colors
+    );
+
+    Ok(display)
+}
+
+fn init_panel<SIZE: DisplaySize>(
+    interface: I2CInterface<I2cdev>,
+    size: SIZE,
+    rotation: DisplayRotation,
+    brightness: Brightness,
+    invert: bool,
+) -> Result<Panel<SIZE>, Box<dyn std::error::Error>> {
+    let mut disp = Ssd1306::new(interface, size, rotation).into_buffered_graphics_mode();
 
     disp.init()
         .map_err(|e| format!("Display initialization error: {:?}", e))?;
 
-    let initial_brightness = map_brightness_value(display_config.brightness);
-
-    disp.set_brightness(initial_brightness)
+    disp.set_brightness(brightness)
         .map_err(|e| format!("Failed to set initial brightness: {:?}", e))?;
 
-    info!(
-        "Display initialized with brightness: {:?}",
-        initial_brightness
-    );
+    if invert {
+        disp.set_invert(true)
+            .map_err(|e| format!("Failed to invert display: {:?}", e))?;
+    }
 
     Ok(disp)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::fs;
+
+    /// Stats with fixed values, so the frames only change with the layout.
+    fn sample_stats() -> SystemStats {
+        SystemStats {
+            ip_address: "192.168.1.42".to_string(),
+            cpu_usage: 12.3,
+            cpu_usage_shown: 12.3,
+            cpu_usage_str: "12.3".to_string(),
+            cpu_per_core: vec![10.0, 20.0, 5.0, 15.0],
+            cpu_temp: 48.2,
+            cpu_temp_str: "48.2".to_string(),
+            ram_usage: 45.6,
+            ram_usage_str: "45.6".to_string(),
+            ram_unit: "%",
+            swap_usage_str: "off".to_string(),
+            hostname: "raspberrypi".to_string(),
+            uptime: 187_980,
+            uptime_str: "2d 4h 13m".to_string(),
+            load_avg_str: "0.52 0.48 0.41".to_string(),
+            disk_usage: 42.0,
+            disk_usage_str: "42.0".to_string(),
+            disk_used: 12_345_678_901,
+            disk_total: 29_394_567_890,
+            net_interface: "eth0".to_string(),
+            net_rx_str: "1.2M/s".to_string(),
+            net_tx_str: "34.5K/s".to_string(),
+            gateway: Some("192.168.1.1".to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Draws `page` on a simulated panel set up by `configure` and returns
+    /// the flushed frame, one line of `#` (lit) and `.` per pixel row.
+    fn render(name: &str, page: Page, configure: impl FnOnce(&mut AppDisplayConfig)) -> String {
+        render_stats(name, page, &sample_stats(), configure)
+    }
+
+    fn render_stats(
+        name: &str,
+        page: Page,
+        stats: &SystemStats,
+        configure: impl FnOnce(&mut AppDisplayConfig),
+    ) -> String {
+        let mut display_config = Config::default().display;
+        configure(&mut display_config);
+        let dir = std::env::temp_dir().join(format!(
+            "rustberry-display-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let mut poe_disp = PoeDisplay::new(&display_config, 0x3C, Some(&dir)).unwrap();
+        poe_disp
+            .update_page(page, stats, false, Point::zero())
+            .unwrap();
+        let frame = fs::read(dir.join("display-0x3C.pbm")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        pbm_to_text(&frame)
+    }
+
+    fn pbm_to_text(pbm: &[u8]) -> String {
+        let header = String::from_utf8_lossy(&pbm[..pbm.len().min(16)]).into_owned();
+        let mut fields = header.split_whitespace();
+        assert_eq!(fields.next(), Some("P4"));
+        let width: usize = fields.next().unwrap().parse().unwrap();
+        let height: usize = fields.next().unwrap().parse().unwrap();
+        let header_len = format!("P4\n{} {}\n", width, height).len();
+        let row_bytes = width.div_ceil(8);
+        let mut text = String::new();
+        for row in pbm[header_len..].chunks(row_bytes).take(height) {
+            for x in 0..width {
+                // PBM uses 1 for black.
+                let lit = row[x / 8] & (0x80 >> (x % 8)) == 0;
+                text.push(if lit { '#' } else { '.' });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Compares the frame with `testdata/frames/<name>.txt`. Run the tests
+    /// with `UPDATE_GOLDEN=1` to write the frames after a deliberate layout
+    /// change, and review them before committing.
+    fn assert_golden(name: &str, frame: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/frames")
+            .join(format!("{}.txt", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, frame).unwrap();
+            return;
+        }
+        let golden = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e));
+        assert!(frame == golden, "frame differs from {:?}:\n{}", path, frame);
+    }
+
+    fn lit(frame: &str, x: usize, y: usize) -> bool {
+        frame.lines().nth(y).unwrap().as_bytes()[x] == b'#'
+    }
+
+    fn small(config: &mut AppDisplayConfig) {
+        config.size = "128x32".to_string();
+    }
+
+    fn tall(config: &mut AppDisplayConfig) {
+        config.size = "128x64".to_string();
+    }
+
+    #[test]
+    fn stats_page_128x32() {
+        let frame = render("stats-128x32", Page::Stats, small);
+        assert_golden("stats-128x32", &frame);
+    }
+
+    #[test]
+    fn stats_page_128x64() {
+        let frame = render("stats-128x64", Page::Stats, tall);
+        assert_golden("stats-128x64", &frame);
+    }
+
+    #[test]
+    fn system_page_128x32() {
+        let frame = render("system-128x32", Page::System, small);
+        assert_golden("system-128x32", &frame);
+    }
+
+    #[test]
+    fn system_page_128x64() {
+        let frame = render("system-128x64", Page::System, tall);
+        assert_golden("system-128x64", &frame);
+    }
+
+    #[test]
+    fn disk_page_128x32() {
+        let frame = render("disk-128x32", Page::Disk, small);
+        assert_golden("disk-128x32", &frame);
+    }
+
+    #[test]
+    fn network_page_128x64() {
+        let frame = render("network-128x64", Page::Network, tall);
+        assert_golden("network-128x64", &frame);
+    }
+
+    #[test]
+    fn stats_page_portrait() {
+        let frame = render("stats-portrait", Page::Stats, |config| {
+            config.rotation = 90;
+        });
+        assert_eq!(frame.lines().count(), 128);
+        assert_golden("stats-portrait", &frame);
+    }
+
+    #[test]
+    fn frame_has_the_panel_size() {
+        for (name, configure, height) in [
+            ("size-128x32", small as fn(&mut AppDisplayConfig), 32),
+            ("size-128x64", tall, 64),
+        ] {
+            let frame = render(name, Page::Stats, configure);
+            assert_eq!(frame.lines().count(), height);
+            assert!(frame.lines().all(|line| line.len() == 128));
+        }
+    }
+
+    #[test]
+    fn hidden_fields_leave_the_panel_blank() {
+        let frame = render("blank", Page::Stats, |config| {
+            small(config);
+            config.show_ip = false;
+            config.show_cpu = false;
+            config.show_temp = false;
+            config.show_ram = false;
+            config.show_rpm = false;
+            config.show_hostname = false;
+        });
+        assert!(!frame.contains('#'));
+    }
+
+    #[test]
+    fn bars_fill_with_the_usage() {
+        // The 1px bar sits on the free line below the IP on 128x32 panels,
+        // `left_column + BAR_LABEL_WIDTH` (56px) wide from x = 2.
+        let frame = render("bars", Page::Stats, |config| {
+            small(config);
+            config.show_bars = true;
+        });
+        let bar_y = 10;
+        let filled = (0..128).filter(|&x| lit(&frame, x, bar_y)).count();
+        assert!(lit(&frame, 2, bar_y));
+        assert!(!lit(&frame, 2 + 56, bar_y));
+        assert_eq!(filled, (56.0 * 0.123_f64).round() as usize);
+    }
+
+    #[test]
+    fn truncates_by_characters() {
+        assert_eq!(truncate_with_ellipsis("raspberrypi", 11), "raspberrypi");
+        assert_eq!(truncate_with_ellipsis("raspberrypi", 8), "raspbe..");
+        // Cutting after 4 bytes would split the second "ü".
+        assert_eq!(truncate_with_ellipsis("büro-küche", 4), "bü..");
+        assert_eq!(truncate_with_ellipsis("büro·küche", 10), "büro·küche");
+    }
+
+    #[test]
+    fn cuts_off_multi_byte_text_when_scrolling_is_off() {
+        let mut stats = sample_stats();
+        stats.hostname = "ü".repeat(40);
+        let frame = render_stats("multi-byte", Page::Stats, &stats, |config| {
+            config.size = "128x64".to_string();
+            config.scroll_speed = 0;
+        });
+        assert!(frame.contains('#'));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::simulator::SimulatedPanel;
+use display_interface::DisplayError;
 use embedded_graphics::{
     image::ImageRaw,
     mono_font::{
@@ -11,8 +13,52 @@ use linux_embedded_hal::I2cdev;
 use profont::PROFONT_12_POINT;
 use ssd1306::{mode::BufferedGraphicsMode, prelude::*, Ssd1306};
 
-pub type Display =
-    Ssd1306<I2CInterface<I2cdev>, DisplaySize128x32, BufferedGraphicsMode<DisplaySize128x32>>;
+/// Panel controls the pages rely on besides drawing. A new kind of panel
+/// implements it and gets a `Display` variant; the pages are drawn through
+/// this trait only.
+pub trait PanelDriver: DrawTarget<Color = BinaryColor, Error = DisplayError> {
+    /// Sends the frame buffer to the panel.
+    fn flush(&mut self) -> Result<(), DisplayError>;
+    fn set_brightness(&mut self, brightness: Brightness) -> Result<(), DisplayError>;
+    fn set_display_on(&mut self, on: bool) -> Result<(), DisplayError>;
+    /// Swaps lit and unlit pixels in the panel itself.
+    fn set_invert(&mut self, invert: bool) -> Result<(), DisplayError>;
+}
+
+pub type Panel<SIZE> = Ssd1306<I2CInterface<I2cdev>, SIZE, BufferedGraphicsMode<SIZE>>;
+
+pub type Display128x32 = Panel<DisplaySize128x32>;
+
+pub type Display128x64 = Panel<DisplaySize128x64>;
+
+impl<SIZE: DisplaySize> PanelDriver for Panel<SIZE> {
+    fn flush(&mut self) -> Result<(), DisplayError> {
+        Ssd1306::flush(self)
+    }
+
+    fn set_brightness(&mut self, brightness: Brightness) -> Result<(), DisplayError> {
+        Ssd1306::set_brightness(self, brightness)
+    }
+
+    fn set_display_on(&mut self, on: bool) -> Result<(), DisplayError> {
+        Ssd1306::set_display_on(self, on)
+    }
+
+    fn set_invert(&mut self, invert: bool) -> Result<(), DisplayError> {
+        Ssd1306::set_invert(self, invert)
+    }
+}
+
+/// The panel size is picked from the config at runtime, so the driver is
+/// wrapped in an enum over the supported sizes, plus the frame buffer used
+/// by `--simulate`. There are only ever a few instances, so the size
+/// difference between the frame buffers is fine.
+#[allow(clippy::large_enum_variant)]
+pub enum Display {
+    Size128x32(Display128x32),
+    Size128x64(Display128x64),
+    Simulated(SimulatedPanel),
+}
 
 pub const PROFONT12: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
     .font(&PROFONT_12_POINT)
@@ -24,11 +70,59 @@ pub const FONT_6X12: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new(
     .text_color(BinaryColor::On)
     .build();
 
+pub const FONT_6X10: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_6X10)
+    .text_color(BinaryColor::On)
+    .build();
+
+pub const FONT_6X10_INVERTED: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_6X10)
+    .text_color(BinaryColor::Off)
+    .background_color(BinaryColor::On)
+    .build();
+
+pub const FONT_9X15: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_9X15)
+    .text_color(BinaryColor::On)
+    .build();
+
+pub const FONT_9X15_INVERTED: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_9X15)
+    .text_color(BinaryColor::Off)
+    .background_color(BinaryColor::On)
+    .build();
+
+pub const FONT_6X13_BOLD: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_6X13_BOLD)
+    .text_color(BinaryColor::On)
+    .build();
+
+pub const FONT_8X13_BOLD: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_8X13_BOLD)
+    .text_color(BinaryColor::On)
+    .build();
+
+pub const FONT_9X15_BOLD: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_9X15_BOLD)
+    .text_color(BinaryColor::On)
+    .build();
+
+pub const FONT_4X6: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_4X6)
+    .text_color(BinaryColor::On)
+    .build();
+
 pub const FONT_5X8: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
     .font(&ascii::FONT_5X8)
     .text_color(BinaryColor::On)
     .build();
 
+pub const FONT_5X8_INVERTED: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&ascii::FONT_5X8)
+    .text_color(BinaryColor::Off)
+    .background_color(BinaryColor::On)
+    .build();
+
 pub const GLYPH_MAPPING: StrGlyphMapping = StrGlyphMapping::new(" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~°", '?' as usize - ' ' as usize);
 
 pub const PCSENIOR8: MonoFont = MonoFont {
@@ -45,3 +139,9 @@ pub const PCSENIOR8_STYLE: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder
     .font(&PCSENIOR8)
     .text_color(BinaryColor::On)
     .build();
+
+pub const PCSENIOR8_INVERTED_STYLE: MonoTextStyle<'_, BinaryColor> = MonoTextStyleBuilder::new()
+    .font(&PCSENIOR8)
+    .text_color(BinaryColor::Off)
+    .background_color(BinaryColor::On)
+    .build();
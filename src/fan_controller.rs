@@ -1,54 +1,664 @@
+use crate::config::FanConfig;
 use anyhow::{anyhow, Result};
+use linux_embedded_hal::gpio_cdev::{Chip, EventRequestFlags, LineRequestFlags};
 use linux_embedded_hal::I2cdev;
-use log::debug;
+use log::{debug, info, warn};
 use pcf857x::OutputPin;
 use pcf857x::{Pcf8574, SlaveAddr};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 const I2C_BUS_PATH: &str = "/dev/i2c-1";
+const GPIO_CHIP_PATH: &str = "/dev/gpiochip0";
+const PWM_CHIP_PATH: &str = "/sys/class/pwm/pwmchip0";
+const PWM_PERIOD: Duration = Duration::from_millis(10);
+const TACH_PULSES_PER_REV: u64 = 2;
+const SELF_TEST_STEP: Duration = Duration::from_secs(2);
+
+type Expander = Arc<Mutex<Pcf8574<I2cdev>>>;
+type ExpanderError = pcf857x::Error<linux_embedded_hal::I2CError>;
+
+/// Expander pin driving the fan. The PoE HAT switches the fan on with a low
+/// level; `invert` is for circuits that need a high level instead.
+#[derive(Clone, Copy)]
+struct FanPin {
+    number: u8,
+    invert: bool,
+}
+
+impl FanPin {
+    /// Whether the pin must be driven high to switch the fan on or off.
+    fn level(self, on: bool) -> bool {
+        on == self.invert
+    }
+
+    fn describe(self, on: bool) -> String {
+        let level = if self.level(on) { "high" } else { "low" };
+        format!("p{}: {}", self.number, level)
+    }
+}
 
 pub struct FanController {
-    expander: Pcf8574<I2cdev>,
+    /// `None` for a simulated controller, which only logs the pin level.
+    expander: Option<Expander>,
+    pin: FanPin,
+    curve: Vec<(f32, u8)>,
+    duty: Arc<AtomicU8>,
+    pwm_running: bool,
+    hardware_pwm: Option<HardwarePwm>,
+    tach: Option<Tachometer>,
+    min_run: Duration,
+    kickstart: Duration,
+    /// End of the current kickstart, during which the fan runs at 100%.
+    kickstart_until: Option<Instant>,
+    last_on: Option<Instant>,
     pub is_running: bool,
     pub temp_on: f32,
     pub temp_off: f32,
 }
 
 impl FanController {
-    pub fn new(temp_on: f32, temp_off: f32) -> Result<Self> {
+    pub fn new(fan_config: &FanConfig) -> Result<Self> {
         debug!("Initializing FanController");
-        if temp_off <= 0.0 || temp_on <= 0.0 {
-            return Err(anyhow!("Temperatures must be greater than 0"));
-        }
-        if temp_on <= temp_off {
-            return Err(anyhow!("temp_on must be greater than temp_off"));
-        }
+        validate(fan_config)?;
 
-        let i2c = I2cdev::new(I2C_BUS_PATH)?;
+        let i2c = I2cdev::new(I2C_BUS_PATH).map_err(|e| {
+            anyhow!(
+                "Failed to open {} for the fan expander: {}. Check that I2C is enabled \
+                 (raspi-config) and that the user is root or in the i2c group",
+                I2C_BUS_PATH,
+                e
+            )
+        })?;
         let address = SlaveAddr::default();
-        let expander = Pcf8574::new(i2c, address);
-        debug!("pcf8574 IO Expander initialized");
+        let expander = Arc::new(Mutex::new(Pcf8574::new(i2c, address)));
+        let pin = FanPin {
+            number: fan_config.gpio_pin,
+            invert: fan_config.invert,
+        };
+        info!(
+            "pcf8574 IO Expander initialized, fan on pin P{} (active {})",
+            pin.number,
+            if pin.invert { "high" } else { "low" }
+        );
+
+        let hardware_pwm = if fan_config.pwm_hardware {
+            match HardwarePwm::new(fan_config.pwm_channel, fan_config.pwm_freq_hz) {
+                Ok(hardware_pwm) => Some(hardware_pwm),
+                Err(e) => {
+                    warn!("Hardware PWM unavailable, using the expander pin: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let tach = fan_config.tach_pin.map(Tachometer::new).transpose()?;
+
+        Ok(Self::build(
+            fan_config,
+            pin,
+            Some(expander),
+            hardware_pwm,
+            tach,
+        ))
+    }
+
+    /// Controller for `--simulate`: runs the same control logic but only logs
+    /// the pin level instead of opening the I2C bus.
+    pub fn simulated(fan_config: &FanConfig) -> Result<Self> {
+        validate(fan_config)?;
+        info!("Simulated fan controller, the fan pin is not written");
+        let pin = FanPin {
+            number: fan_config.gpio_pin,
+            invert: fan_config.invert,
+        };
+        Ok(Self::build(fan_config, pin, None, None, None))
+    }
+
+    fn build(
+        fan_config: &FanConfig,
+        pin: FanPin,
+        expander: Option<Expander>,
+        hardware_pwm: Option<HardwarePwm>,
+        tach: Option<Tachometer>,
+    ) -> Self {
+        let curve = fan_config.curve.clone();
+        let duty = Arc::new(AtomicU8::new(0));
+        let mut pwm_running = false;
+        if let Some(expander) = &expander {
+            if !curve.is_empty() && hardware_pwm.is_none() {
+                debug!("Fan curve configured: {:?}. Starting PWM thread", curve);
+                spawn_pwm_thread(Arc::clone(expander), pin, Arc::clone(&duty));
+                pwm_running = true;
+            }
+        }
 
-        Ok(FanController {
+        FanController {
             expander,
+            pin,
+            curve,
+            duty,
+            pwm_running,
+            hardware_pwm,
+            tach,
+            min_run: Duration::from_secs(fan_config.min_run_secs),
+            kickstart: Duration::from_millis(fan_config.kickstart_ms),
+            kickstart_until: None,
+            last_on: None,
             is_running: false,
-            temp_off,
-            temp_on,
-        })
+            temp_off: fan_config.temp_off(),
+            temp_on: fan_config.temp_on(),
+        }
+    }
+
+    /// Applies reloaded thresholds, curve and minimum run time. The tach pin
+    /// is only read at startup. Leaves the controller untouched when the new
+    /// settings are invalid.
+    pub fn reconfigure(&mut self, fan_config: &FanConfig) -> Result<()> {
+        validate(fan_config)?;
+        self.temp_on = fan_config.temp_on();
+        self.temp_off = fan_config.temp_off();
+        self.curve = fan_config.curve.clone();
+        self.min_run = Duration::from_secs(fan_config.min_run_secs);
+        self.kickstart = Duration::from_millis(fan_config.kickstart_ms);
+        if let Some(expander) = &self.expander {
+            if !self.curve.is_empty() && !self.pwm_running && self.hardware_pwm.is_none() {
+                debug!(
+                    "Fan curve configured: {:?}. Starting PWM thread",
+                    self.curve
+                );
+                spawn_pwm_thread(Arc::clone(expander), self.pin, Arc::clone(&self.duty));
+                self.pwm_running = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Spins the fan up and back down so the wiring can be checked without
+    /// waiting for the CPU to heat up. With a fan curve configured the PWM
+    /// duty cycle is ramped in steps, otherwise the fan is simply switched on.
+    pub fn self_test(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Fan self-test: starting");
+        if self.curve.is_empty() {
+            info!("Fan self-test: fan on");
+            self.fan_on()?;
+            thread::sleep(SELF_TEST_STEP * 2);
+            self.log_test_rpm();
+        } else {
+            for duty in [25, 50, 75, 100] {
+                info!("Fan self-test: duty cycle {}%", duty);
+                self.set_duty(duty)?;
+                self.is_running = true;
+                thread::sleep(SELF_TEST_STEP);
+                self.log_test_rpm();
+            }
+        }
+        info!("Fan self-test: fan off");
+        self.force_off()?;
+        info!("Fan self-test: done");
+        Ok(())
+    }
+
+    fn log_test_rpm(&mut self) {
+        if let Some(rpm) = self.read_rpm() {
+            info!("Fan self-test: {} RPM", rpm);
+        }
+    }
+
+    /// Current duty cycle in percent (0 = off, 100 = full speed).
+    pub fn duty(&self) -> u8 {
+        self.duty.load(Ordering::Relaxed)
+    }
+
+    /// Adjusts the fan for the given temperature. Uses the PWM curve when one
+    /// is configured, otherwise falls back to on/off control between
+    /// `temp_off` and `temp_on`. A fan started from rest at less than full
+    /// duty first runs at 100% for `kickstart_ms`, as a low duty cycle may
+    /// not get it spinning. The kickstart does not block: the first call
+    /// after it is over drops to the curve's duty cycle.
+    pub fn set_speed(&mut self, temp: f32) -> Result<(), Box<dyn std::error::Error>> {
+        if self.curve.is_empty() {
+            if self.is_running {
+                if temp <= self.temp_off {
+                    self.fan_off()?;
+                }
+            } else if temp >= self.temp_on {
+                self.fan_on()?;
+            }
+            return Ok(());
+        }
+
+        let mut duty = curve_duty(&self.curve, temp);
+        if duty == 0 && self.is_running && !self.min_run_elapsed() {
+            duty = self.duty();
+        }
+        let now = Instant::now();
+        if duty > 0 && !self.is_running {
+            self.last_on = Some(now);
+            if duty < 100 && !self.kickstart.is_zero() {
+                debug!("Kickstarting fan at 100% for {:?}", self.kickstart);
+                self.kickstart_until = Some(now + self.kickstart);
+            }
+        }
+        if duty > 0 && self.kickstart_until.is_some_and(|until| now < until) {
+            duty = 100;
+        } else {
+            self.kickstart_until = None;
+        }
+        if duty != self.duty() {
+            debug!("Setting fan duty cycle to {}% at {:.1}°C", duty, temp);
+            self.set_duty(duty)?;
+        }
+        self.is_running = duty > 0;
+        Ok(())
+    }
+
+    /// Whether the speed follows the PWM curve rather than on/off control.
+    pub fn uses_curve(&self) -> bool {
+        !self.curve.is_empty()
+    }
+
+    /// Fan speed computed from the tachometer edges counted since the last
+    /// call. Returns `None` when no tach pin is configured.
+    pub fn read_rpm(&mut self) -> Option<u32> {
+        self.tach.as_mut().map(Tachometer::read_rpm)
+    }
+
+    /// Stores the duty cycle for the software PWM thread, or writes it to the
+    /// hardware PWM channel when one is in use.
+    fn set_duty(&mut self, duty: u8) -> Result<(), Box<dyn std::error::Error>> {
+        self.duty.store(duty, Ordering::Relaxed);
+        if let Some(hardware_pwm) = &self.hardware_pwm {
+            hardware_pwm
+                .set_duty(duty)
+                .map_err(|e| format!("Failed to set hardware PWM duty cycle: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Whether the fan has been running for at least `min_run_secs`.
+    fn min_run_elapsed(&self) -> bool {
+        self.last_on
+            .is_none_or(|last_on| last_on.elapsed() >= self.min_run)
     }
 
     pub fn fan_on(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        debug!("Sending fan on signal [p0: low]");
-        let mut parts = self.expander.split();
-        parts.p0.set_low().unwrap();
+        if !self.is_running {
+            self.last_on = Some(Instant::now());
+        }
+        if self.curve.is_empty() && self.hardware_pwm.is_none() {
+            self.write_fan_pin(true)?;
+        }
+        self.set_duty(100)?;
         self.is_running = true;
         Ok(())
     }
 
     pub fn fan_off(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        debug!("Sending fan off signal [p0: high]");
-        let mut parts = self.expander.split();
-        parts.p0.set_high().unwrap();
+        if self.is_running && !self.min_run_elapsed() {
+            debug!(
+                "Fan minimum run time of {}s not reached, keeping fan on",
+                self.min_run.as_secs()
+            );
+            return Ok(());
+        }
+        self.force_off()
+    }
+
+    /// Turns the fan off immediately, ignoring `min_run_secs`.
+    /// The pin is written directly even in PWM mode so the fan is off by the
+    /// time this returns, e.g. right before the process exits.
+    pub fn force_off(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.kickstart_until = None;
+        self.set_duty(0)?;
+        if self.hardware_pwm.is_none() {
+            self.write_fan_pin(false)?;
+        }
         self.is_running = false;
         Ok(())
     }
+
+    fn write_fan_pin(&self, on: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let state = if on { "on" } else { "off" };
+        match &self.expander {
+            Some(expander) => {
+                debug!("Sending fan {} signal [{}]", state, self.pin.describe(on));
+                write_pin(expander, self.pin, on).map_err(|e| {
+                    format!("Failed to write fan pin P{}: {:?}", self.pin.number, e)
+                })?;
+            }
+            None => debug!("Simulated fan {} signal [{}]", state, self.pin.describe(on)),
+        }
+        Ok(())
+    }
+}
+
+fn validate(fan_config: &FanConfig) -> Result<()> {
+    let temp_on = fan_config.temp_on();
+    let temp_off = fan_config.temp_off();
+    let curve = &fan_config.curve;
+    if temp_off <= 0.0 || temp_on <= 0.0 {
+        return Err(anyhow!("Temperatures must be greater than 0"));
+    }
+    if temp_on <= temp_off {
+        return Err(anyhow!("temp_on must be greater than temp_off"));
+    }
+    if curve.iter().any(|&(_, duty)| duty > 100) {
+        return Err(anyhow!("Fan curve duty cycles must be between 0 and 100"));
+    }
+    if curve.windows(2).any(|w| w[0].0 >= w[1].0) {
+        return Err(anyhow!(
+            "Fan curve temperatures must be in strictly ascending order"
+        ));
+    }
+    if fan_config.gpio_pin > 7 {
+        return Err(anyhow!("gpio_pin must be an expander pin between 0 and 7"));
+    }
+    if fan_config.pwm_hardware && (fan_config.pwm_channel > 1 || fan_config.pwm_freq_hz == 0) {
+        return Err(anyhow!(
+            "pwm_channel must be 0 or 1 and pwm_freq_hz greater than 0"
+        ));
+    }
+    Ok(())
+}
+
+/// Explains the usual causes of a GPIO line that cannot be requested.
+pub fn gpio_error(setting: &str, pin: u32, error: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!(
+        "Failed to request GPIO {} ({}) on {}: {}. Check that the user is root or \
+         in the gpio group and that no other program or overlay uses the pin",
+        pin,
+        setting,
+        GPIO_CHIP_PATH,
+        error
+    )
+}
+
+fn write_pin(expander: &Expander, pin: FanPin, on: bool) -> Result<(), ExpanderError> {
+    let expander = expander.lock().unwrap();
+    set_pin(&expander, pin.number, pin.level(on))
+}
+
+/// Drives expander pin `pin` (P0-P7). The pins are distinct types, hence the
+/// match.
+fn set_pin(expander: &Pcf8574<I2cdev>, pin: u8, high: bool) -> Result<(), ExpanderError> {
+    let mut parts = expander.split();
+    macro_rules! set {
+        ($pin:expr) => {
+            if high {
+                $pin.set_high()
+            } else {
+                $pin.set_low()
+            }
+        };
+    }
+    match pin {
+        0 => set!(parts.p0),
+        1 => set!(parts.p1),
+        2 => set!(parts.p2),
+        3 => set!(parts.p3),
+        4 => set!(parts.p4),
+        5 => set!(parts.p5),
+        6 => set!(parts.p6),
+        _ => set!(parts.p7),
+    }
+}
+
+/// Linearly interpolates the duty cycle for `temp` between the curve points.
+/// Below the first point the fan is off, above the last point it keeps the
+/// last point's duty.
+fn curve_duty(curve: &[(f32, u8)], temp: f32) -> u8 {
+    let (first_temp, _) = curve[0];
+    let (last_temp, last_duty) = curve[curve.len() - 1];
+    if temp < first_temp {
+        return 0;
+    }
+    if temp >= last_temp {
+        return last_duty;
+    }
+
+    curve
+        .windows(2)
+        .find(|w| temp >= w[0].0 && temp < w[1].0)
+        .map(|w| {
+            let (t0, d0) = w[0];
+            let (t1, d1) = w[1];
+            let ratio = (temp - t0) / (t1 - t0);
+            (d0 as f32 + ratio * (d1 as f32 - d0 as f32)).round() as u8
+        })
+        .unwrap_or(last_duty)
+}
+
+/// Software PWM on the expander pin. The expander is an I2C device so the
+/// frequency is kept low; the pin is only written when its level changes.
+fn spawn_pwm_thread(expander: Expander, pin: FanPin, duty: Arc<AtomicU8>) {
+    thread::spawn(move || {
+        let mut fan_on: Option<bool> = None;
+        let mut set_fan = |on: bool| {
+            if fan_on != Some(on) {
+                let expander = expander.lock().unwrap();
+                match set_pin(&expander, pin.number, pin.level(on)) {
+                    Ok(()) => fan_on = Some(on),
+                    Err(e) => warn!("Failed to write fan PWM pin: {:?}", e),
+                }
+            }
+        };
+
+        loop {
+            match duty.load(Ordering::Relaxed) {
+                0 => {
+                    set_fan(false);
+                    thread::sleep(PWM_PERIOD);
+                }
+                d if d >= 100 => {
+                    set_fan(true);
+                    thread::sleep(PWM_PERIOD);
+                }
+                d => {
+                    let on_time = PWM_PERIOD * d as u32 / 100;
+                    set_fan(true);
+                    thread::sleep(on_time);
+                    set_fan(false);
+                    thread::sleep(PWM_PERIOD - on_time);
+                }
+            }
+        }
+    });
+}
+
+/// Hardware PWM channel of the Pi (needs `dtoverlay=pwm`), driven through
+/// sysfs. Channel 0 is usually GPIO 18, channel 1 GPIO 19. The output is
+/// high for the duty cycle.
+struct HardwarePwm {
+    path: PathBuf,
+    period_ns: u64,
+}
+
+impl HardwarePwm {
+    fn new(channel: u8, freq_hz: u32) -> Result<Self> {
+        let chip = Path::new(PWM_CHIP_PATH);
+        let path = chip.join(format!("pwm{}", channel));
+        if !path.exists() {
+            fs::write(chip.join("export"), channel.to_string())
+                .map_err(|e| anyhow!("Failed to export PWM channel {}: {}", channel, e))?;
+            // udev needs a moment to make the new channel's files writable.
+            thread::sleep(Duration::from_millis(100));
+        }
+        let period_ns = 1_000_000_000 / freq_hz.max(1) as u64;
+        // The duty cycle may never exceed the period, so clear it first.
+        fs::write(path.join("duty_cycle"), "0")?;
+        fs::write(path.join("period"), period_ns.to_string())?;
+        fs::write(path.join("enable"), "1")?;
+        info!(
+            "Hardware PWM initialized on channel {} at {} Hz",
+            channel, freq_hz
+        );
+        Ok(HardwarePwm { path, period_ns })
+    }
+
+    fn set_duty(&self, duty: u8) -> std::io::Result<()> {
+        let duty_ns = self.period_ns * duty.min(100) as u64 / 100;
+        fs::write(self.path.join("duty_cycle"), duty_ns.to_string())
+    }
+}
+
+struct Tachometer {
+    edges: Arc<AtomicU64>,
+    last_edges: u64,
+    last_read: Instant,
+}
+
+impl Tachometer {
+    fn new(pin: u32) -> Result<Self> {
+        let events = Chip::new(GPIO_CHIP_PATH)
+            .and_then(|mut chip| chip.get_line(pin))
+            .and_then(|line| {
+                line.events(
+                    LineRequestFlags::INPUT,
+                    EventRequestFlags::RISING_EDGE,
+                    "rustberry-poe-monitor",
+                )
+            })
+            .map_err(|e| gpio_error("tach_pin", pin, e))?;
+        debug!("Tachometer initialized on GPIO {}", pin);
+
+        // Edge events are read on a dedicated thread so the main loop never
+        // waits on the pin; it only samples the counter.
+        let edges = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&edges);
+        thread::spawn(move || {
+            for event in events {
+                match event {
+                    Ok(_) => {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => {
+                        warn!("Tachometer event error, stopping RPM sampling: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Tachometer {
+            edges,
+            last_edges: 0,
+            last_read: Instant::now(),
+        })
+    }
+
+    fn read_rpm(&mut self) -> u32 {
+        let now = Instant::now();
+        let edges = self.edges.load(Ordering::Relaxed);
+        let elapsed = now.duration_since(self.last_read).as_secs_f64();
+        let delta = edges - self.last_edges;
+        self.last_edges = edges;
+        self.last_read = now;
+
+        if elapsed <= 0.0 {
+            return 0;
+        }
+        let revolutions = delta as f64 / TACH_PULSES_PER_REV as f64;
+        (revolutions * 60.0 / elapsed).round() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fan_pin_is_active_low_by_default() {
+        let pin = FanPin {
+            number: 0,
+            invert: false,
+        };
+        assert!(!pin.level(true));
+        assert!(pin.level(false));
+        assert_eq!(pin.describe(true), "p0: low");
+    }
+
+    #[test]
+    fn inverted_fan_pin_is_active_high() {
+        let pin = FanPin {
+            number: 3,
+            invert: true,
+        };
+        assert!(pin.level(true));
+        assert!(!pin.level(false));
+        assert_eq!(pin.describe(true), "p3: high");
+    }
+
+    const CURVE: [(f32, u8); 3] = [(40.0, 20), (60.0, 60), (70.0, 100)];
+
+    #[test]
+    fn curve_is_off_below_the_first_point() {
+        assert_eq!(curve_duty(&CURVE, 20.0), 0);
+        assert_eq!(curve_duty(&CURVE, 39.9), 0);
+        assert_eq!(curve_duty(&CURVE, 40.0), 20);
+    }
+
+    #[test]
+    fn curve_interpolates_between_points() {
+        assert_eq!(curve_duty(&CURVE, 50.0), 40);
+        assert_eq!(curve_duty(&CURVE, 55.0), 50);
+        assert_eq!(curve_duty(&CURVE, 60.0), 60);
+        assert_eq!(curve_duty(&CURVE, 65.0), 80);
+    }
+
+    #[test]
+    fn curve_keeps_the_last_duty_above_the_last_point() {
+        assert_eq!(curve_duty(&CURVE, 70.0), 100);
+        assert_eq!(curve_duty(&CURVE, 95.0), 100);
+    }
+
+    #[test]
+    fn unsorted_curve_is_rejected() {
+        // `curve_duty` relies on ascending temperatures, so such a curve
+        // never reaches it.
+        let mut fan_config = crate::config::Config::default().fan;
+        fan_config.curve = vec![(60.0, 60), (40.0, 20), (70.0, 100)];
+        assert!(FanController::simulated(&fan_config).is_err());
+        let mut fan = FanController::simulated(&crate::config::Config::default().fan).unwrap();
+        assert!(fan.reconfigure(&fan_config).is_err());
+        assert!(!fan.uses_curve());
+    }
+
+    #[test]
+    fn kickstart_runs_at_full_speed_without_blocking() {
+        let mut fan_config = crate::config::Config::default().fan;
+        fan_config.curve = CURVE.to_vec();
+        fan_config.kickstart_ms = 200;
+        let mut fan = FanController::simulated(&fan_config).unwrap();
+
+        let started = Instant::now();
+        fan.set_speed(50.0).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert_eq!(fan.duty(), 100);
+        fan.set_speed(50.0).unwrap();
+        assert_eq!(fan.duty(), 100);
+
+        thread::sleep(Duration::from_millis(250));
+        fan.set_speed(50.0).unwrap();
+        assert_eq!(fan.duty(), 40);
+        assert!(fan.is_running);
+    }
+
+    #[test]
+    fn kickstart_only_runs_from_rest() {
+        let mut fan_config = crate::config::Config::default().fan;
+        fan_config.curve = CURVE.to_vec();
+        fan_config.kickstart_ms = 5000;
+        let mut fan = FanController::simulated(&fan_config).unwrap();
+        // Starting at full duty needs no kickstart, and a running fan
+        // follows the curve straight away.
+        fan.set_speed(80.0).unwrap();
+        fan.set_speed(65.0).unwrap();
+        assert_eq!(fan.duty(), 80);
+    }
 }
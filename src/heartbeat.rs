@@ -0,0 +1,40 @@
+use crate::fan_controller::gpio_error;
+use anyhow::Result;
+use linux_embedded_hal::gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use log::{info, warn};
+
+const GPIO_CHIP_PATH: &str = "/dev/gpiochip0";
+
+/// LED on a GPIO line that is toggled on every loop iteration, so a steady
+/// blink shows the monitor is alive even with the display off.
+pub struct HeartbeatLed {
+    line: LineHandle,
+    lit: bool,
+}
+
+impl HeartbeatLed {
+    pub fn new(pin: u32) -> Result<Self> {
+        let line = Chip::new(GPIO_CHIP_PATH)
+            .and_then(|mut chip| chip.get_line(pin))
+            .and_then(|line| line.request(LineRequestFlags::OUTPUT, 0, "rustberry-poe-monitor"))
+            .map_err(|e| gpio_error("heartbeat gpio_pin", pin, e))?;
+        info!("Heartbeat LED initialized on GPIO {}", pin);
+        Ok(HeartbeatLed { line, lit: false })
+    }
+
+    pub fn toggle(&mut self) {
+        self.set(!self.lit);
+    }
+
+    /// Switches the LED off, e.g. before the process exits.
+    pub fn off(&mut self) {
+        self.set(false);
+    }
+
+    fn set(&mut self, lit: bool) {
+        match self.line.set_value(lit as u8) {
+            Ok(()) => self.lit = lit,
+            Err(e) => warn!("Failed to set the heartbeat LED: {}", e),
+        }
+    }
+}
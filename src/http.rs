@@ -0,0 +1,97 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+/// Time a client has to send its whole request. The servers handle one
+/// client at a time, so a slow one must not hold up the others for long.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Longest request or header line accepted, in bytes.
+pub const MAX_LINE_LEN: usize = 1024;
+
+/// Sets up `stream` for a single request: writes time out after
+/// `CLIENT_TIMEOUT`, and so does reading the request through the returned
+/// reader, counted from now.
+pub fn request_reader(stream: &TcpStream) -> io::Result<BufReader<DeadlineReader<'_>>> {
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    Ok(BufReader::new(DeadlineReader {
+        stream,
+        deadline: Instant::now() + CLIENT_TIMEOUT,
+    }))
+}
+
+/// Reads from the client until `deadline`, however the bytes trickle in.
+pub struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Reads one line like `read_line`, failing on lines longer than
+/// `MAX_LINE_LEN` instead of buffering whatever the client sends.
+pub fn read_line_capped(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let len = reader.take(MAX_LINE_LEN as u64 + 1).read_line(line)?;
+    if len > MAX_LINE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request line too long",
+        ));
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn read_line_capped_reads_a_line() {
+        let mut reader = Cursor::new("POST /fan/on HTTP/1.1\r\nHost: pi\r\n");
+        let mut line = String::new();
+        read_line_capped(&mut reader, &mut line).unwrap();
+        assert_eq!(line, "POST /fan/on HTTP/1.1\r\n");
+    }
+
+    #[test]
+    fn read_line_capped_rejects_long_lines() {
+        let long = format!("GET /{} HTTP/1.1\r\n", "a".repeat(MAX_LINE_LEN));
+        let mut reader = Cursor::new(long);
+        let mut line = String::new();
+        let error = read_line_capped(&mut reader, &mut line).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(line.len() <= MAX_LINE_LEN + 1);
+    }
+
+    #[test]
+    fn request_reader_gives_up_on_a_trickling_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let started = Instant::now();
+        let sender = std::thread::spawn(move || {
+            // One byte every 100ms and never a newline.
+            while client.write_all(b"G").is_ok() && started.elapsed() < CLIENT_TIMEOUT * 2 {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        let mut reader = request_reader(&server).unwrap();
+        let mut line = String::new();
+        assert!(read_line_capped(&mut reader, &mut line).is_err());
+        assert!(started.elapsed() < CLIENT_TIMEOUT + Duration::from_millis(500));
+        drop(reader);
+        drop(server);
+        sender.join().unwrap();
+    }
+}
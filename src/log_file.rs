@@ -0,0 +1,106 @@
+use crate::config::LogConfig;
+use chrono::Utc;
+use env_logger::{Builder, Env};
+use log::{info, LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+static LOG_FILE: OnceLock<Mutex<LogFile>> = OnceLock::new();
+
+/// `[log]` file; once it reaches `max_size_kb` it is moved to `<file>.1`
+/// (replacing the previous one) and a fresh file is started.
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    level: LevelFilter,
+}
+
+impl LogFile {
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        if self.max_size > 0 && self.size >= self.max_size {
+            let mut rotated = self.path.clone().into_os_string();
+            rotated.push(".1");
+            fs::rename(&self.path, &rotated)?;
+            self.file = open(&self.path)?;
+            self.size = 0;
+        }
+        let line = format!(
+            "[{} {:<5} {}] {}\n",
+            Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// `env_logger` on the console, plus the log file once one is opened.
+struct Logger {
+    console: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata)
+            || LOG_FILE
+                .get()
+                .is_some_and(|file| metadata.level() <= file.lock().unwrap().level)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.console.matches(record) {
+            self.console.log(record);
+        }
+        if let Some(file) = LOG_FILE.get() {
+            let mut file = file.lock().unwrap();
+            if record.level() <= file.level {
+                // Nowhere to report a failed write but the console.
+                if let Err(e) = file.write(record) {
+                    eprintln!("Failed to write log file {:?}: {}", file.path, e);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+    }
+}
+
+/// Logs to the console as configured by `RUST_LOG`, `info` by default.
+pub fn init() {
+    let console = Builder::from_env(Env::default().default_filter_or("info")).build();
+    log::set_max_level(console.filter());
+    log::set_logger(Box::leak(Box::new(Logger { console })))
+        .expect("logger is only initialized once");
+}
+
+/// Also writes the log messages up to `log.level` to `file`.
+pub fn open_file(file: &str, log_config: &LogConfig) -> io::Result<()> {
+    let path = PathBuf::from(file);
+    let log_file = open(&path)?;
+    let level = log_config.level.parse().unwrap_or(LevelFilter::Info);
+    let log_file = LogFile {
+        size: log_file.metadata()?.len(),
+        path,
+        file: log_file,
+        max_size: log_config.max_size_kb * 1024,
+        level,
+    };
+    if LOG_FILE.set(Mutex::new(log_file)).is_ok() {
+        log::set_max_level(log::max_level().max(level));
+        info!("Logging to {} at level {}", file, log_config.level);
+    }
+    Ok(())
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
@@ -0,0 +1,71 @@
+use log::warn;
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+/// An iteration taking longer than this many refresh intervals, sleep
+/// included, is logged as slow.
+const SLOW_ITERATION_FACTOR: u32 = 2;
+
+/// Parts of a main loop iteration that are timed separately.
+#[derive(Clone, Copy)]
+pub enum Phase {
+    /// Host info and the readings the stats are made of.
+    Stats,
+    Fan,
+    /// Screen timeout, wake-ups and drawing the pages.
+    Display,
+    /// Config reload, pixel shift, publishing and the other bookkeeping.
+    Other,
+    Sleep,
+}
+
+const PHASE_NAMES: [&str; 5] = ["stats", "fan", "display", "other", "sleep"];
+
+/// Times the phases of one main loop iteration, so stalls such as an I2C
+/// hiccup or a slow `hostname` show up in the log along with the phase they
+/// happened in. While nothing is slow this costs one timestamp per phase.
+pub struct LoopTimer {
+    start: Instant,
+    mark: Instant,
+    phases: [Duration; PHASE_NAMES.len()],
+}
+
+impl LoopTimer {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        LoopTimer {
+            start: now,
+            mark: now,
+            phases: [Duration::ZERO; PHASE_NAMES.len()],
+        }
+    }
+
+    /// Adds the time since the previous phase ended to `phase`.
+    pub fn end(&mut self, phase: Phase) {
+        let now = Instant::now();
+        self.phases[phase as usize] += now - self.mark;
+        self.mark = now;
+    }
+
+    /// Warns when the iteration took more than `SLOW_ITERATION_FACTOR`
+    /// refresh intervals, listing the phases from the slowest.
+    pub fn finish(self, refresh_interval: Duration) {
+        let total = self.mark - self.start;
+        if total <= refresh_interval * SLOW_ITERATION_FACTOR {
+            return;
+        }
+        let mut phases: Vec<_> = PHASE_NAMES.into_iter().zip(self.phases).collect();
+        phases.sort_by_key(|&(_, duration)| Reverse(duration));
+        let breakdown = phases
+            .iter()
+            .map(|(name, duration)| format!("{} {:.2}s", name, duration.as_secs_f64()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        warn!(
+            "Slow main loop iteration: {:.2}s with a {:?} refresh interval ({})",
+            total.as_secs_f64(),
+            refresh_interval,
+            breakdown
+        );
+    }
+}
@@ -1,48 +1,319 @@
+use chrono::{Local, Timelike};
+use clap::Parser;
 use embedded_graphics::prelude::Point;
-use env_logger::{Builder, Env};
-use log::{debug, info, trace};
-use ssd1306::prelude::Brightness;
+use log::{debug, error, info, trace, warn};
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
-use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+use sysinfo::System;
+
+mod board;
+
+mod button;
+use button::Button;
+
+mod heartbeat;
+use heartbeat::HeartbeatLed;
 
 mod fan_controller;
 use fan_controller::FanController;
 
 mod config;
-use config::Config;
+use config::{Config, DisplayConfig, FanConfig, TemperatureConfig};
 
 mod display;
-use display::PoeDisplay;
+use display::{Page, PoeDisplay};
 
 mod display_types;
 
+mod http;
+
+mod metrics;
+use metrics::MetricsServer;
+
+mod network;
+use network::{IpRange, IpVersion, NetworkMonitor};
+
+mod throttle;
+
+mod mqtt;
+use mqtt::MqttPublisher;
+
+mod alerts;
+use alerts::AlertNotifier;
+
+mod log_file;
+
+mod loop_timer;
+use loop_timer::{LoopTimer, Phase};
+
+mod stats_log;
+use stats_log::StatsLogger;
+
+mod status_socket;
+use status_socket::StatusSocket;
+
+mod systemd;
+use systemd::Notifier;
+
+mod simulator;
+use simulator::Simulation;
+
+mod stats_provider;
+use stats_provider::{StatsProvider, SystemProvider};
+
+mod power;
+use power::PowerSensor;
+
+mod control;
+use control::ControlServer;
+
+/// Consecutive failed display updates that are answered with a reconnect.
+const DISPLAY_MAX_RECONNECTS: u32 = 5;
+const DISPLAY_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const DISPLAY_INIT_MAX_DELAY: Duration = Duration::from_secs(16);
+
 struct AppState {
     last_shift_time: Instant,
     shift_index: usize,
+    /// State of the generator behind `shift_mode = "random"`.
+    shift_rng: u64,
     shift_offset: Point,
     last_periodic_toggle_time: Instant,
     is_display_periodically_on: bool,
     screen_dimmed: bool,
+    screen_fading: bool,
+    last_activity_time: Instant,
+    /// IP address, fan state and CPU temperature at the last activity.
+    activity_ip: String,
+    activity_fan_running: bool,
+    activity_temp: Option<f32>,
+    /// Pages cycled through, indexed by `current_page`.
+    pages: Vec<Page>,
+    current_page: usize,
+    last_page_time: Instant,
+    hostname: String,
+    last_hostname_refresh: Instant,
+    ip_address: String,
+    last_ip_refresh: Instant,
+    /// The displays blink until then after an IP address change.
+    ip_flash_until: Option<Instant>,
+    ip_flash_inverted: bool,
+    critical_readings: u32,
+    critical_shutdown_started: bool,
+    smoothed_temp: Option<f32>,
+    /// The last CPU temperature read failed; the fan runs at full speed
+    /// and the last good reading is shown until the sensor is back.
+    temp_missing: bool,
+    /// Consecutive loop iterations that failed to drive the displays.
+    display_failures: u32,
+    displays_disabled: bool,
+    /// The display is off for the night schedule.
+    night_off: bool,
+    /// Display state forced through the control server.
+    manual_display: Option<bool>,
+    /// The fan runs at full speed until then after a `SIGUSR1`.
+    max_cool_until: Option<Instant>,
+    temp_history: VecDeque<f32>,
+    /// Latest CPU usage readings, averaged for the display.
+    cpu_samples: VecDeque<f32>,
+    last_temp_sample: Option<Instant>,
+    /// Set once `vcgencmd measure_temp` failed, so it is not retried.
+    gpu_temp_missing: bool,
+    /// Set once the CPU frequency could not be read, so it is not retried.
+    cpu_freq_missing: bool,
+    /// Set once neither the sysfs `get_throttled` file nor `vcgencmd
+    /// get_throttled` could be read, so they are not retried.
+    throttle_missing: bool,
+    last_display_reinit: Instant,
 }
 
+impl AppState {
+    /// State before the first loop iteration, with everything timed from
+    /// now.
+    fn new(pages: Vec<Page>, hostname: String, ip_address: String, shift_rng: u64) -> Self {
+        let now = Instant::now();
+        AppState {
+            pages,
+            last_shift_time: now,
+            shift_index: 0,
+            shift_rng,
+            shift_offset: Point::new(0, 0),
+            last_periodic_toggle_time: now,
+            is_display_periodically_on: true,
+            screen_dimmed: false,
+            screen_fading: false,
+            last_activity_time: now,
+            activity_ip: ip_address.clone(),
+            activity_fan_running: false,
+            activity_temp: None,
+            current_page: 0,
+            last_page_time: now,
+            hostname,
+            last_hostname_refresh: now,
+            ip_address,
+            last_ip_refresh: now,
+            ip_flash_until: None,
+            ip_flash_inverted: false,
+            critical_readings: 0,
+            critical_shutdown_started: false,
+            smoothed_temp: None,
+            temp_missing: false,
+            display_failures: 0,
+            displays_disabled: false,
+            night_off: false,
+            manual_display: None,
+            max_cool_until: None,
+            temp_history: VecDeque::new(),
+            cpu_samples: VecDeque::new(),
+            last_temp_sample: None,
+            gpu_temp_missing: false,
+            cpu_freq_missing: false,
+            throttle_missing: false,
+            last_display_reinit: now,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Default))]
 struct SystemStats {
     ip_address: String,
-    cpu_usage: String,
+    cpu_usage: f32,
+    /// `cpu_usage` averaged over `display.cpu_average_samples` refreshes.
+    #[serde(skip)]
+    cpu_usage_shown: f32,
+    #[serde(skip)]
+    cpu_usage_str: String,
+    cpu_per_core: Vec<f32>,
     cpu_temp: f32,
+    #[serde(skip)]
     cpu_temp_str: String,
-    ram_usage: String,
+    ram_usage: f64,
+    /// Used RAM in percent, or used/total when `display.mem_format` is set
+    /// to an absolute format.
+    #[serde(skip)]
+    ram_usage_str: String,
+    /// `%`, or the `G`/`M` unit of the absolute RAM usage.
+    #[serde(skip)]
+    ram_unit: &'static str,
+    /// Swap usage in percent; `None` when no swap is configured.
+    swap_usage: Option<f64>,
+    #[serde(skip)]
+    swap_usage_str: String,
+    swap_warning: bool,
     hostname: String,
+    fan_rpm: Option<u32>,
+    uptime: u64,
+    #[serde(skip)]
+    uptime_str: String,
+    load_avg: [f64; 3],
+    #[serde(skip)]
+    load_avg_str: String,
+    disk_usage: f64,
+    #[serde(skip)]
+    disk_usage_str: String,
+    disk_warning: bool,
+    disk_used: u64,
+    disk_total: u64,
+    net_interface: String,
+    net_rx: f64,
+    #[serde(skip)]
+    net_rx_str: String,
+    net_tx: f64,
+    #[serde(skip)]
+    net_tx_str: String,
+    /// Router of the default route; `None` without one.
+    gateway: Option<String>,
+    throttle_status: Vec<String>,
+    /// Reported by `vcgencmd` when `temperature.gpu` is on.
+    gpu_temp: Option<f32>,
+    #[serde(skip)]
+    gpu_temp_str: String,
+    /// Current CPU clock in MHz when `display.show_cpu_freq` is on.
+    cpu_freq: Option<u32>,
+    #[serde(skip)]
+    cpu_freq_str: String,
+    /// INA219 readings; `None` without a power sensor.
+    power_voltage: Option<f32>,
+    power_current: Option<f32>,
+    power_watts: Option<f32>,
+    /// Recent CPU temperatures for the sparkline, oldest first.
+    #[serde(skip)]
+    temp_history: Vec<f32>,
+    /// Text of the QR code page; `None` without a `qr_template` or while
+    /// the IP address it needs is unknown.
+    #[serde(skip)]
+    qr_text: Option<String>,
+    /// Local time in `display.time_format`.
+    time: String,
+    /// `display.summary_fields` joined into one line, e.g. `42.1C 12.3% 64.0%`;
+    /// `None` without summary fields.
+    #[serde(skip)]
+    summary: Option<String>,
+}
+
+/// Directory of the simulated frames when `--simulate` names none.
+const DEFAULT_FRAME_DIR: &str = "frames";
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Path of the config file
+    /// [default: $HOME/.config/rustberry-poe-monitor/config.toml]
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Run the fan briefly at startup to check that it spins
+    #[arg(long)]
+    test_fan: bool,
+    /// Run without fan control, e.g. for testing on hardware without the HAT
+    #[arg(long)]
+    no_fan: bool,
+    /// Print the settings in effect, after the environment overrides and
+    /// board defaults, as TOML and exit
+    #[arg(long)]
+    print_config: bool,
+    /// Run without a Pi: frames are written to DIR as PBM images (view with
+    /// e.g. `feh --reload 1`, or convert with `pnmtopng`), the fan is only
+    /// logged and the CPU temperature is simulated
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = DEFAULT_FRAME_DIR)]
+    simulate: Option<PathBuf>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let env = Env::default().default_filter_or("info");
-    Builder::from_env(env).init();
+    let mut args = Args::parse();
+
+    log_file::init();
 
-    let config = Config::load()?;
+    let config_path = match args.config {
+        Some(path) => path,
+        None => Config::default_path()?,
+    };
+    if args.print_config {
+        let config = Config::read_from(&config_path)?;
+        print!(
+            "# Effective settings for {}\n{}",
+            config_path.display(),
+            config.into_toml()?
+        );
+        return Ok(());
+    }
+    let mut config = Config::load_from(&config_path)?;
+    if let Some(file) = &config.log.file {
+        if let Err(e) = log_file::open_file(file, &config.log) {
+            warn!(
+                "Failed to open log file {}: {}. Logging to the console only.",
+                file, e
+            );
+        }
+    }
 
     let version = env!("CARGO_PKG_VERSION");
 
@@ -53,20 +324,161 @@ fn main() -> Result<(), Box<dyn Error>> {
     debug!("Target Family:           {}", std::env::consts::FAMILY);
     debug!("Target Architecture:     {}", std::env::consts::ARCH);
     debug!("Config loaded: {:?}", config);
+    if config.display.always_on {
+        info!("display.always_on is set, screen timeout, schedule and periodic off are disabled");
+    }
+    if config.display.reduced_motion {
+        info!("display.reduced_motion is set, pixel shift, periodic off, scrolling and blinking are disabled");
+    }
+    match board::model() {
+        Some(model) => info!("Board: {}", model),
+        None => info!("Board model unknown, using generic defaults"),
+    }
+    // E.g. a dev machine: without the display bus, the panel, fan and
+    // sensors can only fail, so run as with `--simulate`.
+    if args.simulate.is_none()
+        && !board::is_raspberry_pi()
+        && !Path::new(&config.display.i2c_bus).exists()
+    {
+        warn!(
+            "Not running on a Raspberry Pi and {} is missing; hardware features disabled, simulating with frames in {:?}",
+            config.display.i2c_bus, DEFAULT_FRAME_DIR
+        );
+        args.simulate = Some(PathBuf::from(DEFAULT_FRAME_DIR));
+    }
+    if args.simulate.is_none() {
+        match read_cpu_temperature(&config.temperature) {
+            Some(temp) => info!(
+                "CPU temperature from {}: {:.1}°C",
+                config.temperature.temp_source, temp
+            ),
+            None => warn!(
+                "No CPU temperature from temperature.temp_source {}, the fan runs at full speed until there is one",
+                config.temperature.temp_source
+            ),
+        }
+    }
 
-    let mut poe_disp = PoeDisplay::new(&config.display)?;
+    let mut displays: Vec<PoeDisplay> = config
+        .display_configs()
+        .iter()
+        .filter_map(|display_config| open_display(display_config, args.simulate.as_deref()))
+        .collect();
 
-    let mut fan_controller = FanController::new(config.fan.temp_on, config.fan.temp_off)?;
-    info!(
-        "Fan controller initialized. temp-on: {}, temp-off: {}",
-        fan_controller.temp_on, fan_controller.temp_off
-    );
+    let splash_start = Instant::now();
+    if !config.splash_duration().is_zero() {
+        for poe_disp in displays.iter_mut() {
+            if let Err(e) = poe_disp.show_splash(version) {
+                warn!("Failed to show splash screen: {:?}", e);
+            }
+        }
+    }
 
-    let mut sys: System = System::new_with_specifics(
-        RefreshKind::nothing()
-            .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
-            .with_memory(MemoryRefreshKind::nothing().with_ram()),
-    );
+    let mut fan_controller = if args.no_fan {
+        warn!("Started with --no-fan, fan control is disabled");
+        None
+    } else {
+        let fan_controller = if args.simulate.is_some() {
+            FanController::simulated(&config.fan)?
+        } else {
+            FanController::new(&config.fan)
+                .map_err(|e| format!("{}. Use --no-fan to start without fan control.", e))?
+        };
+        info!(
+            "Fan controller initialized. temp-on: {}, temp-off: {}",
+            fan_controller.temp_on, fan_controller.temp_off
+        );
+        Some(fan_controller)
+    };
+
+    if args.test_fan {
+        match &mut fan_controller {
+            Some(fan_controller) => fan_controller.self_test()?,
+            None => warn!("Fan self-test skipped, fan control is disabled"),
+        }
+    }
+
+    let button = match config.button.gpio_pin {
+        Some(_) if args.simulate.is_some() => {
+            info!("Button disabled while simulating");
+            None
+        }
+        Some(pin) => Some(Button::new(pin, &config.button)?),
+        None => None,
+    };
+
+    let mut heartbeat = match config.heartbeat.gpio_pin {
+        Some(_) if args.simulate.is_some() => {
+            info!("Heartbeat LED disabled while simulating");
+            None
+        }
+        Some(pin) => Some(HeartbeatLed::new(pin)?),
+        None => None,
+    };
+
+    let mut power_sensor = match config.power.i2c_address {
+        Some(_) if args.simulate.is_some() => {
+            info!("Power sensor disabled while simulating");
+            None
+        }
+        Some(address) => PowerSensor::new(&config.power, address),
+        None => None,
+    };
+
+    let metrics_server = if config.metrics.enabled {
+        MetricsServer::start(config.metrics.port)
+    } else {
+        None
+    };
+
+    let control_server = if config.control.enabled {
+        ControlServer::start(&config.control)
+    } else {
+        None
+    };
+
+    let status_socket = config
+        .status_socket
+        .path
+        .as_deref()
+        .and_then(StatusSocket::start);
+
+    let mqtt_publisher = if config.mqtt.enabled {
+        Some(MqttPublisher::start(&config.mqtt))
+    } else {
+        None
+    };
+
+    let mut alert_notifier = config
+        .alerts
+        .webhook_url
+        .as_deref()
+        .map(|url| AlertNotifier::start(&config.alerts, url, config.display.use_fahrenheit()));
+
+    if args.simulate.is_none() {
+        info!(
+            "Reading CPU temperature from {} ({})",
+            config
+                .temperature
+                .sensor_paths()
+                .collect::<Vec<_>>()
+                .join(", "),
+            config.temperature.combine
+        );
+    }
+
+    let mut stats_logger = config
+        .logging
+        .file
+        .as_deref()
+        .map(|file| StatsLogger::new(&config.logging, file));
+
+    let mut stats_provider: Box<dyn StatsProvider> = if args.simulate.is_some() {
+        Box::new(Simulation::new())
+    } else {
+        Box::new(SystemProvider::new())
+    };
+    let mut network_monitor = NetworkMonitor::new();
 
     debug!("System initialized. System info:");
     debug!("================================");
@@ -83,128 +495,784 @@ fn main() -> Result<(), Box<dyn Error>> {
         System::os_version().unwrap_or_default()
     );
 
+    // Keep the splash up for the rest of its duration after the startup work.
+    thread::sleep(
+        config
+            .splash_duration()
+            .saturating_sub(splash_start.elapsed()),
+    );
+
+    let mut ip_version = network::map_ip_version_value(&config.network.ip_version);
+
+    let ip_address = wait_for_ip_address(&config, ip_version, stats_provider.as_ref());
     info!("Starting main loop");
 
-    fan_controller.fan_off()?;
-
-    let screen_timeout_duration = config.display_timeout();
-    let periodic_on_duration = config.periodic_on_duration();
-    let periodic_off_duration = config.periodic_off_duration();
-    let shift_interval = Duration::from_secs(60);
-    let shift_pattern = [Point::new(0, 0), Point::new(1, 0)];
-    let refresh_interval = config.refresh_interval();
-
-    let mut app_state = AppState {
-        last_shift_time: Instant::now(),
-        shift_index: 0,
-        shift_offset: Point::new(0, 0),
-        last_periodic_toggle_time: Instant::now(),
-        is_display_periodically_on: true,
-        screen_dimmed: false,
-    };
+    let pages = Page::cycle(
+        power_sensor.is_some(),
+        config.display.qr_template.is_some(),
+        config.display.show_clock,
+    );
+    let mut app_state = AppState::new(
+        pages,
+        stats_provider.hostname(),
+        ip_address,
+        shift_seed(config.display.shift_seed),
+    );
+
+    if let Some(fan_controller) = &mut fan_controller {
+        start_fan(fan_controller, &config, stats_provider.as_ref())?;
+    }
 
-    let start_time = Instant::now();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))?;
+    }
+    let reload = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&reload))?;
+    let max_cool = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGUSR1, Arc::clone(&max_cool))?;
 
-    loop {
+    let notifier = Notifier::from_env();
+    if let Some(notifier) = &notifier {
+        notifier.ready();
+    }
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let mut timer = LoopTimer::start();
+        if reload.swap(false, Ordering::Relaxed) {
+            reload_config(
+                &config_path,
+                &mut config,
+                fan_controller.as_mut(),
+                &mut displays,
+            );
+            ip_version = network::map_ip_version_value(&config.network.ip_version);
+            app_state.pages = Page::cycle(
+                power_sensor.is_some(),
+                config.display.qr_template.is_some(),
+                config.display.show_clock,
+            );
+            app_state.current_page %= app_state.pages.len();
+            // A changed brightness shows at once unless the screen is dimmed;
+            // otherwise it applies when the screen wakes up.
+            if !app_state.displays_disabled && !app_state.screen_dimmed && !app_state.screen_fading
+            {
+                for poe_disp in &mut displays {
+                    if let Err(e) = poe_disp.restore_brightness() {
+                        warn!("Failed to apply display brightness: {:?}", e);
+                    }
+                }
+            }
+        }
+
+        timer.end(Phase::Other);
         let now = Instant::now();
 
-        handle_screen_timeout(
-            start_time,
+        // Display errors are collected instead of ending the loop, so the fan
+        // keeps being controlled while the panel is reconnected.
+        let displays_up = !app_state.displays_disabled;
+        let display_override = control_server
+            .as_ref()
+            .and_then(ControlServer::display_override);
+        let mut display_result = if displays_up {
+            handle_display_override(now, display_override, &mut app_state, &mut displays)
+        } else {
+            Ok(())
+        };
+        if displays_up && display_result.is_ok() && app_state.manual_display.is_none() {
+            display_result = if config.display.always_on {
+                handle_always_on(now, &mut app_state, &mut displays)
+            } else {
+                handle_screen_timeout(
+                    now,
+                    config.display_timeout(),
+                    config.dim_fade(),
+                    &mut app_state,
+                    &mut displays,
+                )
+                .and_then(|()| {
+                    handle_schedule(config.night_window(), &mut app_state, &mut displays)
+                })
+                .and_then(|()| {
+                    if app_state.night_off {
+                        return Ok(());
+                    }
+                    handle_periodic_display(
+                        &config,
+                        now,
+                        config.periodic_on_duration(),
+                        config.periodic_off_duration(),
+                        &mut app_state,
+                        &mut displays,
+                    )
+                })
+            };
+        }
+        timer.end(Phase::Display);
+
+        if config.display.reduced_motion {
+            app_state.shift_offset = Point::zero();
+        } else {
+            update_pixel_shift(
+                now,
+                config.shift_interval(),
+                &config.shift_pattern(),
+                config.shift_range(),
+                &mut app_state,
+            );
+        }
+
+        let qr_available = qr_text(&config, &app_state.ip_address, &app_state.hostname).is_some();
+        advance_page(now, config.page_interval(), qr_available, &mut app_state);
+        timer.end(Phase::Other);
+
+        let previous_ip = refresh_host_info(
+            stats_provider.as_ref(),
             now,
-            screen_timeout_duration,
+            config.network.ip_interface.as_deref(),
+            ip_version,
+            &config.ip_skip_ranges(),
             &mut app_state,
-            &mut poe_disp,
-        )?;
+        );
+        if let Some(previous_ip) = previous_ip {
+            if let Some(mqtt_publisher) = &mqtt_publisher {
+                mqtt_publisher.publish_ip(&previous_ip, &app_state.ip_address);
+            }
+            if config.network.ip_change_flash_secs > 0 && !config.display.reduced_motion {
+                app_state.ip_flash_until =
+                    Some(now + Duration::from_secs(config.network.ip_change_flash_secs));
+            }
+        }
 
-        handle_periodic_display(
+        let stats = gather_stats(
+            stats_provider.as_mut(),
+            &mut network_monitor,
+            fan_controller.as_mut(),
+            power_sensor.as_mut(),
             &config,
-            now,
-            periodic_on_duration,
-            periodic_off_duration,
+            ip_version,
             &mut app_state,
-            &mut poe_disp,
-        )?;
+        );
+        timer.end(Phase::Stats);
 
-        update_pixel_shift(now, shift_interval, &shift_pattern, &mut app_state);
+        if max_cool.swap(false, Ordering::Relaxed) {
+            if fan_controller.is_some() {
+                let duration = Duration::from_secs(config.fan.max_cool_secs);
+                info!(
+                    "SIGUSR1 received. Running the fan at full speed for {:?}.",
+                    duration
+                );
+                app_state.max_cool_until = Some(now + duration);
+            } else {
+                warn!("SIGUSR1 received, but fan control is disabled");
+            }
+        }
+        // A control server override wins over the max cool run. Without a
+        // temperature reading the fan runs at full speed, whatever else was
+        // asked for.
+        let fan_temp = match stats.gpu_temp {
+            Some(gpu_temp) if config.temperature.fan_include_gpu => stats.cpu_temp.max(gpu_temp),
+            _ => stats.cpu_temp,
+        };
+        let fan_override = if app_state.temp_missing {
+            Some(true)
+        } else {
+            control_server
+                .as_ref()
+                .and_then(|control_server| {
+                    control_server.fan_override(fan_temp, config.fan.temp_on())
+                })
+                .or_else(|| max_cool_active(now, &mut app_state).then_some(true))
+        };
+        if let Some(fan_controller) = &mut fan_controller {
+            if let Some(on) = fan_override {
+                handle_fan_override(fan_controller, on, &config.fan, mqtt_publisher.as_ref())?;
+            } else {
+                handle_fan_control(
+                    fan_controller,
+                    fan_temp,
+                    &config.fan,
+                    mqtt_publisher.as_ref(),
+                )?;
+            }
+        }
+        let fan_running = fan_controller.as_ref().is_some_and(|fc| fc.is_running);
+        timer.end(Phase::Fan);
 
-        let stats = gather_stats(&mut sys);
+        handle_critical_temperature(
+            stats.cpu_temp,
+            &config.fan,
+            stats_provider.is_simulated(),
+            &mut app_state,
+            &mut displays,
+        );
 
-        handle_fan_control(&mut fan_controller, stats.cpu_temp)?;
+        if displays_up && display_result.is_ok() {
+            display_result = handle_temperature_wake(
+                now,
+                stats.cpu_temp,
+                config.fan.temp_on(),
+                &mut app_state,
+                &mut displays,
+            );
+        }
+        if displays_up && display_result.is_ok() {
+            display_result = handle_activity(
+                now,
+                &stats,
+                fan_running,
+                config.display.activity_temp_delta,
+                &mut app_state,
+                &mut displays,
+            );
+        }
 
-        if app_state.is_display_periodically_on {
-            poe_disp
-                .update(
-                    &stats.ip_address,
-                    stats.cpu_usage,
-                    stats.cpu_temp_str,
-                    stats.ram_usage,
-                    &stats.hostname,
-                    app_state.shift_offset,
-                )
-                .map_err(|e| format!("Display update error: {:?}", e))?;
+        if displays_up && display_result.is_ok() {
+            display_result = handle_ip_flash(
+                now,
+                config.display.invert_colors,
+                &mut app_state,
+                &mut displays,
+            );
         }
+        timer.end(Phase::Display);
 
-        thread::sleep(refresh_interval);
+        if let Some(metrics_server) = &metrics_server {
+            metrics_server.update(&stats, fan_running);
+        }
+        if let Some(status_socket) = &status_socket {
+            status_socket.update(&stats, fan_running);
+        }
+
+        if let Some(mqtt_publisher) = &mqtt_publisher {
+            mqtt_publisher.publish_stats(&stats);
+        }
+
+        if let Some(stats_logger) = &mut stats_logger {
+            stats_logger.record(now, &stats, fan_running);
+        }
+
+        if let Some(alert_notifier) = &mut alert_notifier {
+            alert_notifier.check(now, &stats);
+        }
+
+        if let Some(button) = &button {
+            let presses = button.take_presses();
+            if presses > 0 && displays_up && display_result.is_ok() {
+                display_result = handle_button(now, presses, &mut app_state, &mut displays);
+            }
+        }
+
+        timer.end(Phase::Other);
+
+        if displays_up
+            && display_result.is_ok()
+            && !app_state.night_off
+            && app_state.is_display_periodically_on
+            && app_state.manual_display != Some(false)
+            && !app_state.critical_shutdown_started
+        {
+            if let Some(interval) = config.reinit_interval() {
+                handle_display_reinit(now, interval, &mut app_state, &mut displays);
+            }
+            display_result = update_displays(
+                &stats,
+                fan_running,
+                config.display.cooldown_page,
+                &app_state,
+                &mut displays,
+            );
+        }
+
+        if displays_up {
+            match display_result {
+                Ok(()) => app_state.display_failures = 0,
+                Err(e) => handle_display_error(e, &mut app_state, &mut displays),
+            }
+        }
+        timer.end(Phase::Display);
+
+        if let Some(notifier) = &notifier {
+            notifier.watchdog();
+        }
+        if let Some(heartbeat) = &mut heartbeat {
+            heartbeat.toggle();
+        }
+
+        timer.end(Phase::Other);
+
+        thread::sleep(config.refresh_interval());
+        timer.end(Phase::Sleep);
+        timer.finish(config.refresh_interval());
+    }
+
+    info!("Shutdown signal received. Clearing display and turning fan off.");
+    for poe_disp in &mut displays {
+        if let Err(e) = poe_disp.clear() {
+            warn!("Failed to clear display: {:?}", e);
+        }
+    }
+    if let Some(heartbeat) = &mut heartbeat {
+        heartbeat.off();
+    }
+    if let Some(fan_controller) = &mut fan_controller {
+        fan_controller.force_off()?;
+    }
+    Ok(())
+}
+
+/// Draws the current page on every display. Displays without an assigned
+/// page follow the page cycle.
+fn update_displays(
+    stats: &SystemStats,
+    fan_running: bool,
+    cooldown_page: bool,
+    state: &AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    // While the fan runs the cooldown page takes the place of the cycle;
+    // displays pinned to a page keep it.
+    let cycled_page = if cooldown_page && fan_running {
+        Page::Cooldown
+    } else {
+        state.pages[state.current_page]
+    };
+    for poe_disp in displays {
+        let page = poe_disp.page().unwrap_or(cycled_page);
+        poe_disp
+            .update_page(page, stats, fan_running, state.shift_offset)
+            .map_err(|e| format!("Display update error: {:?}", e))?;
+    }
+    Ok(())
+}
+
+/// Opens a display, retrying with a doubling delay because the panel may
+/// not answer yet right after a cold boot. `None` once `init_attempts` are
+/// used up, so the monitor runs without that display.
+fn open_display(display_config: &DisplayConfig, frames_dir: Option<&Path>) -> Option<PoeDisplay> {
+    let address = display_config.i2c_address;
+    let attempts = display_config.init_attempts;
+    let mut delay = DISPLAY_RECONNECT_DELAY;
+    for attempt in 1..=attempts {
+        match PoeDisplay::new(display_config, address, frames_dir) {
+            Ok(poe_disp) => return Some(poe_disp),
+            Err(e) if attempt < attempts => {
+                warn!(
+                    "Failed to initialize display 0x{:02X} (attempt {}/{}): {}. Retrying in {:?}.",
+                    address, attempt, attempts, e, delay
+                );
+                thread::sleep(delay);
+                delay = (delay * 2).min(DISPLAY_INIT_MAX_DELAY);
+            }
+            Err(e) => error!(
+                "Failed to initialize display 0x{:02X} after {} attempts: {}. Continuing without it.",
+                address, attempts, e
+            ),
+        }
+    }
+    None
+}
+
+/// Reconnects the displays after a failed update. After
+/// `DISPLAY_MAX_RECONNECTS` failures in a row the displays are left alone
+/// and only the fan is controlled.
+fn handle_display_error(error: Box<dyn Error>, state: &mut AppState, displays: &mut [PoeDisplay]) {
+    state.display_failures += 1;
+    if state.display_failures > DISPLAY_MAX_RECONNECTS {
+        error!(
+            "{}. Giving up on the display after {} reconnects; fan control continues.",
+            error, DISPLAY_MAX_RECONNECTS
+        );
+        state.displays_disabled = true;
+        return;
+    }
+    warn!(
+        "{}. Reconnecting display (attempt {}/{}).",
+        error, state.display_failures, DISPLAY_MAX_RECONNECTS
+    );
+    thread::sleep(DISPLAY_RECONNECT_DELAY);
+    reconnect_displays(state, displays);
+}
+
+/// Re-initializes the displays every `interval`, so a controller that
+/// silently stopped taking updates does not freeze on a stale frame. Only
+/// called while the displays are on, as initializing switches them on.
+fn handle_display_reinit(
+    now: Instant,
+    interval: Duration,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) {
+    if now.duration_since(state.last_display_reinit) < interval {
+        return;
     }
+    state.last_display_reinit = now;
+    debug!("Re-initializing displays after {:?}", interval);
+    reconnect_displays(state, displays);
+}
+
+/// Reconnects every display, keeping a dimmed screen dimmed. Failures are
+/// logged; the next update reports a display that is still unreachable.
+fn reconnect_displays(state: &AppState, displays: &mut [PoeDisplay]) {
+    for poe_disp in displays {
+        if let Err(e) = poe_disp.reconnect() {
+            warn!("Failed to reconnect display: {}", e);
+            continue;
+        }
+        if state.screen_dimmed {
+            if let Err(e) = poe_disp.fade_brightness(1.0) {
+                warn!("Failed to dim reconnected display: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Re-reads the config file on SIGHUP. Hardware settings (display size,
+/// rotation, tach pin) and the metrics/MQTT services keep their startup
+/// values; an invalid file is logged and the running config is kept.
+fn reload_config(
+    config_path: &Path,
+    config: &mut Config,
+    fan_controller: Option<&mut FanController>,
+    displays: &mut [PoeDisplay],
+) {
+    info!("SIGHUP received. Reloading config.");
+    let new_config = match Config::load_from(config_path) {
+        Ok(new_config) => new_config,
+        Err(e) => {
+            warn!("Failed to reload config, keeping the current one: {}", e);
+            return;
+        }
+    };
+    if let Some(fan_controller) = fan_controller {
+        if let Err(e) = fan_controller.reconfigure(&new_config.fan) {
+            warn!("Invalid fan config, keeping the current one: {}", e);
+            return;
+        }
+    }
+    let display_configs = new_config.display_configs();
+    if display_configs.len() != config.display_configs().len() {
+        warn!("Displays can only be added or removed with a restart");
+    }
+    // Matched by address, since displays that failed to start are missing.
+    for poe_disp in displays.iter_mut() {
+        if let Some(display_config) = display_configs
+            .iter()
+            .find(|display_config| display_config.i2c_address == poe_disp.address())
+        {
+            poe_disp.reconfigure(display_config);
+        }
+    }
+    *config = new_config;
+    info!(
+        "Config reloaded. temp-on: {}, temp-off: {}",
+        config.fan.temp_on(),
+        config.fan.temp_off()
+    );
+    debug!("Config loaded: {:?}", config);
 }
 
 fn handle_screen_timeout(
-    start_time: Instant,
     now: Instant,
     timeout_duration: Duration,
+    fade_duration: Duration,
     state: &mut AppState,
-    poe_disp: &mut PoeDisplay,
+    displays: &mut [PoeDisplay],
 ) -> Result<(), Box<dyn Error>> {
-    let elapsed_time = now.duration_since(start_time);
-    if timeout_duration.as_secs() > 0 && !state.screen_dimmed && elapsed_time >= timeout_duration {
+    let elapsed_time = now.duration_since(state.last_activity_time);
+    if timeout_duration.as_secs() == 0 || state.screen_dimmed || elapsed_time < timeout_duration {
+        return Ok(());
+    }
+    if !state.screen_fading {
         info!("Screen timeout reached. Dimming display.");
+        state.screen_fading = true;
+    }
+    // The fade advances by one step per loop iteration and ends at DIMMEST.
+    let progress = if fade_duration.is_zero() {
+        1.0
+    } else {
+        (elapsed_time - timeout_duration).as_secs_f32() / fade_duration.as_secs_f32()
+    };
+    for poe_disp in displays {
         poe_disp
-            .set_brightness(Brightness::DIMMEST)
+            .fade_brightness(progress)
             .map_err(|e| format!("Failed to dim display: {:?}", e))?;
+    }
+    if progress >= 1.0 {
+        state.screen_fading = false;
         state.screen_dimmed = true;
     }
     Ok(())
 }
 
+/// Brightens a dimmed screen while the CPU is at or above the fan-on
+/// threshold. Every hot sample restarts the screen timeout, so the display
+/// dims again once the temperature has stayed below it for the timeout.
+fn handle_temperature_wake(
+    now: Instant,
+    cpu_temp: f32,
+    temp_on: f32,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    if cpu_temp < temp_on {
+        return Ok(());
+    }
+    state.last_activity_time = now;
+    if state.screen_dimmed || state.screen_fading {
+        let reason = format!("CPU temperature {:.1}°C reached fan-on threshold", cpu_temp);
+        wake_displays(&reason, state, displays)?;
+    }
+    Ok(())
+}
+
+/// Restarts the screen timeout when the IP address changes, the fan
+/// switches on or off, or the CPU temperature has moved by `temp_delta`
+/// since the last activity (0 ignores the temperature).
+fn handle_activity(
+    now: Instant,
+    stats: &SystemStats,
+    fan_running: bool,
+    temp_delta: f32,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    let activity_temp = *state.activity_temp.get_or_insert(stats.cpu_temp);
+    let reason = if stats.ip_address != state.activity_ip {
+        format!("IP address changed to {}", stats.ip_address)
+    } else if fan_running != state.activity_fan_running {
+        format!("Fan switched {}", if fan_running { "on" } else { "off" })
+    } else if temp_delta > 0.0 && (stats.cpu_temp - activity_temp).abs() >= temp_delta {
+        format!("CPU temperature changed to {:.1}°C", stats.cpu_temp)
+    } else {
+        return Ok(());
+    };
+    debug!("Activity: {}", reason);
+    state.activity_ip.clone_from(&stats.ip_address);
+    state.activity_fan_running = fan_running;
+    state.activity_temp = Some(stats.cpu_temp);
+    state.last_activity_time = now;
+    wake_displays(&reason, state, displays)
+}
+
+/// Returns a dimmed or dimming screen to the configured brightness.
+fn wake_displays(
+    reason: &str,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    if !state.screen_dimmed && !state.screen_fading {
+        return Ok(());
+    }
+    info!("{}. Waking display.", reason);
+    for poe_disp in displays {
+        poe_disp
+            .restore_brightness()
+            .map_err(|e| format!("Failed to restore display brightness: {:?}", e))?;
+    }
+    state.screen_dimmed = false;
+    state.screen_fading = false;
+    Ok(())
+}
+
+/// A button press wakes a dimmed display, restarts the screen timeout and
+/// shows the next page.
+fn handle_button(
+    now: Instant,
+    presses: u32,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    state.last_activity_time = now;
+    wake_displays("Button pressed", state, displays)?;
+    state.current_page = (state.current_page + presses as usize) % state.pages.len();
+    state.last_page_time = now;
+    debug!(
+        "Switching to display page {:?}",
+        state.pages[state.current_page]
+    );
+    Ok(())
+}
+
+/// Applies a display override from the control server. While one is active
+/// the screen timeout, night schedule and periodic cycle are paused; when it
+/// ends the display comes back at full brightness and they take over again.
+fn handle_display_override(
+    now: Instant,
+    display_override: Option<bool>,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    if display_override == state.manual_display {
+        return Ok(());
+    }
+    match display_override {
+        Some(false) => {
+            info!("Manual override: display off");
+            for poe_disp in displays.iter_mut() {
+                poe_disp
+                    .display_off()
+                    .map_err(|e| format!("Failed to turn display off: {:?}", e))?;
+            }
+        }
+        Some(true) | None => {
+            if display_override.is_some() {
+                info!("Manual override: display on");
+            } else {
+                info!("Manual display override ended");
+            }
+            show_displays(now, state, displays)?;
+        }
+    }
+    state.manual_display = display_override;
+    Ok(())
+}
+
+/// Keeps the display on at its configured brightness while
+/// `display.always_on` is set, undoing a dim or off state left over from
+/// before a reload.
+fn handle_always_on(
+    now: Instant,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    if state.screen_dimmed
+        || state.screen_fading
+        || state.night_off
+        || !state.is_display_periodically_on
+    {
+        info!("Display always on, waking it up");
+        show_displays(now, state, displays)?;
+    }
+    Ok(())
+}
+
+/// Turns the displays on at their configured brightness and resets the
+/// screen timeout, schedule and periodic off state.
+fn show_displays(
+    now: Instant,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    for poe_disp in displays.iter_mut() {
+        poe_disp
+            .display_on()
+            .and_then(|()| poe_disp.restore_brightness())
+            .map_err(|e| format!("Failed to turn display on: {:?}", e))?;
+    }
+    state.screen_dimmed = false;
+    state.screen_fading = false;
+    state.night_off = false;
+    state.is_display_periodically_on = true;
+    state.last_activity_time = now;
+    state.last_periodic_toggle_time = now;
+    Ok(())
+}
+
+/// Switches the display off while the local time is inside the
+/// `[schedule]` window. The periodic on/off cycle is paused meanwhile.
+fn handle_schedule(
+    night_window: Option<(u32, u32)>,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    let in_window = night_window.is_some_and(|(start, end)| {
+        let local = Local::now();
+        let minute = local.hour() * 60 + local.minute();
+        if start <= end {
+            (start..end).contains(&minute)
+        } else {
+            minute >= start || minute < end
+        }
+    });
+    if in_window && !state.night_off {
+        info!("Night schedule: turning display off.");
+        for poe_disp in displays {
+            poe_disp
+                .display_off()
+                .map_err(|e| format!("Failed to turn display off for the night: {:?}", e))?;
+        }
+        state.night_off = true;
+    } else if !in_window && state.night_off {
+        info!("Night schedule: turning display on.");
+        if state.is_display_periodically_on {
+            for poe_disp in displays {
+                poe_disp
+                    .display_on()
+                    .map_err(|e| format!("Failed to turn display on after the night: {:?}", e))?;
+            }
+        }
+        state.night_off = false;
+    }
+    Ok(())
+}
+
 fn handle_periodic_display(
     config: &Config,
     now: Instant,
     on_duration: Duration,
     off_duration: Duration,
     state: &mut AppState,
-    poe_disp: &mut PoeDisplay,
+    displays: &mut [PoeDisplay],
 ) -> Result<(), Box<dyn Error>> {
-    if config.display.enable_periodic_off {
+    if config.display.periodic_off() {
         let time_since_last_toggle = now.duration_since(state.last_periodic_toggle_time);
 
         if state.is_display_periodically_on && time_since_last_toggle >= on_duration {
             debug!("Periodic timer: Turning display OFF.");
-            poe_disp
-                .display_off()
-                .map_err(|e| format!("Failed periodic display OFF: {:?}", e))?;
+            for poe_disp in displays {
+                poe_disp
+                    .display_off()
+                    .map_err(|e| format!("Failed periodic display OFF: {:?}", e))?;
+            }
             state.is_display_periodically_on = false;
             state.last_periodic_toggle_time = now;
         } else if !state.is_display_periodically_on && time_since_last_toggle >= off_duration {
             debug!("Periodic timer: Turning display ON.");
+            for poe_disp in displays {
+                poe_disp
+                    .display_on()
+                    .map_err(|e| format!("Failed periodic display ON: {:?}", e))?;
+            }
+            state.is_display_periodically_on = true;
+            state.last_periodic_toggle_time = now;
+        }
+    } else if !state.is_display_periodically_on {
+        // Periodic off was disabled by a reload while the display was off.
+        for poe_disp in displays {
             poe_disp
                 .display_on()
                 .map_err(|e| format!("Failed periodic display ON: {:?}", e))?;
-            state.is_display_periodically_on = true;
-            state.last_periodic_toggle_time = now;
         }
+        state.is_display_periodically_on = true;
     }
     Ok(())
 }
 
+/// Moves to the next `shift_pattern` offset, or with a `shift_range` to a
+/// random offset between 0 and it. Like the pattern offsets these are never
+/// negative, which would clip text aligned to the left or top edge.
 fn update_pixel_shift(
     now: Instant,
     shift_interval: Duration,
     shift_pattern: &[Point],
+    shift_range: Option<Point>,
     state: &mut AppState,
 ) {
     if now.duration_since(state.last_shift_time) >= shift_interval {
-        state.shift_index = (state.shift_index + 1) % shift_pattern.len();
-        state.shift_offset = shift_pattern[state.shift_index];
+        match shift_range {
+            Some(range) => {
+                let mut random_offset = |max: i32| {
+                    let span = (max + 1) as u64;
+                    (next_random(&mut state.shift_rng) % span) as i32
+                };
+                state.shift_offset = Point::new(random_offset(range.x), random_offset(range.y));
+            }
+            None => {
+                state.shift_index = (state.shift_index + 1) % shift_pattern.len();
+                state.shift_offset = shift_pattern[state.shift_index];
+            }
+        }
         state.last_shift_time = now;
         debug!(
             "Shifting display pixels to offset: {:?}",
@@ -213,30 +1281,379 @@ fn update_pixel_shift(
     }
 }
 
-fn gather_stats(sys: &mut System) -> SystemStats {
-    sys.refresh_cpu_usage();
-    sys.refresh_memory();
+/// Start state for `next_random`: `shift_seed`, or the clock without one.
+fn shift_seed(seed: Option<u64>) -> u64 {
+    let seed = seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+    // splitmix64 finalizer, so small seeds do not start with small numbers.
+    let mut mixed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    mixed ^= mixed >> 31;
+    // xorshift never leaves 0.
+    mixed.max(1)
+}
+
+/// xorshift64 step; plenty for picking shift offsets. `state` must not be 0.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Moves on to the next page, skipping the QR code page while it has
+/// nothing to show.
+fn advance_page(now: Instant, page_interval: Duration, qr_available: bool, state: &mut AppState) {
+    if !page_interval.is_zero() && now.duration_since(state.last_page_time) >= page_interval {
+        state.current_page = (state.current_page + 1) % state.pages.len();
+        if state.pages[state.current_page] == Page::Qr && !qr_available {
+            state.current_page = (state.current_page + 1) % state.pages.len();
+        }
+        state.last_page_time = now;
+        debug!(
+            "Switching to display page {:?}",
+            state.pages[state.current_page]
+        );
+    }
+}
+
+const HOSTNAME_REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+const IP_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
 
-    let ip_address = get_ip_address();
-    let hostname = get_hostname();
-    let cpu_temp = get_cpu_temperature();
-    let cpu_temp_str = format!("{:.1}", cpu_temp);
-    let cpu_usage = format!("{:.1}", sys.global_cpu_usage());
-    let ram_usage = format!("{:.1}", get_ram_usage(sys));
+/// Hostname and IP rarely change, so they are re-read on their own, much
+/// longer intervals instead of on every refresh. Returns the previous IP
+/// address when it changed.
+fn refresh_host_info(
+    stats_provider: &dyn StatsProvider,
+    now: Instant,
+    ip_interface: Option<&str>,
+    ip_version: IpVersion,
+    ip_skip: &[IpRange],
+    state: &mut AppState,
+) -> Option<String> {
+    if now.duration_since(state.last_hostname_refresh) >= HOSTNAME_REFRESH_INTERVAL {
+        state.hostname = stats_provider.hostname();
+        state.last_hostname_refresh = now;
+        trace!("Refreshed hostname: {}", state.hostname);
+    }
+    if now.duration_since(state.last_ip_refresh) < IP_REFRESH_INTERVAL {
+        return None;
+    }
+    let ip_address = stats_provider.ip_address(ip_interface, ip_version, ip_skip);
+    state.last_ip_refresh = now;
+    trace!("Refreshed IP address: {}", ip_address);
+    if ip_address == state.ip_address {
+        return None;
+    }
+    warn!(
+        "IP address changed from {} to {}",
+        state.ip_address, ip_address
+    );
+    Some(std::mem::replace(&mut state.ip_address, ip_address))
+}
+
+/// Blinks the displays by inverting them on every other refresh until
+/// `ip_flash_until`, then returns them to the configured inversion.
+fn handle_ip_flash(
+    now: Instant,
+    invert_colors: bool,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) -> Result<(), Box<dyn Error>> {
+    let Some(until) = state.ip_flash_until else {
+        return Ok(());
+    };
+    let flashing = now < until;
+    state.ip_flash_inverted = flashing && !state.ip_flash_inverted;
+    if !flashing {
+        state.ip_flash_until = None;
+    }
+    for poe_disp in displays {
+        poe_disp
+            .set_invert(invert_colors != state.ip_flash_inverted)
+            .map_err(|e| format!("Failed to flash display: {:?}", e))?;
+    }
+    Ok(())
+}
+
+fn gather_stats(
+    stats_provider: &mut dyn StatsProvider,
+    network_monitor: &mut NetworkMonitor,
+    fan_controller: Option<&mut FanController>,
+    power_sensor: Option<&mut PowerSensor>,
+    config: &Config,
+    ip_version: IpVersion,
+    state: &mut AppState,
+) -> SystemStats {
+    stats_provider.refresh();
+
+    let ip_address = state.ip_address.clone();
+    let hostname = state.hostname.clone();
+    // A missing reading is not taken for a cold CPU: the last good one
+    // stays on the display and the main loop runs the fan at full speed.
+    let reading = stats_provider.cpu_temperature(&config.temperature);
+    match (reading, state.temp_missing) {
+        (None, false) => warn!("No CPU temperature, running the fan at full speed"),
+        (Some(_), true) => info!("CPU temperature available again"),
+        _ => {}
+    }
+    state.temp_missing = reading.is_none();
+    let cpu_temp = match reading {
+        Some(reading) => {
+            let cpu_temp =
+                smooth_temperature(reading, state.smoothed_temp, config.fan.temp_smoothing);
+            state.smoothed_temp = Some(cpu_temp);
+            record_temp_sample(
+                cpu_temp,
+                config.display.sparkline_samples,
+                config.sparkline_interval(),
+                state,
+            );
+            Some(cpu_temp)
+        }
+        None => state.smoothed_temp,
+    };
+    let temp_history = state.temp_history.iter().copied().collect();
+    let qr_text = qr_text(config, &ip_address, &hostname);
+    let cpu_temp_str = cpu_temp
+        .map(|cpu_temp| format_temperature(cpu_temp, config))
+        .unwrap_or_else(|| "--".to_string());
+    let cpu_temp = cpu_temp.unwrap_or(0.0);
+    let gpu_temp = if config.temperature.gpu && !state.gpu_temp_missing {
+        let gpu_temp = stats_provider.gpu_temperature();
+        if gpu_temp.is_none() {
+            warn!("vcgencmd measure_temp failed, not reading the GPU temperature again");
+            state.gpu_temp_missing = true;
+        }
+        gpu_temp
+    } else {
+        None
+    };
+    let gpu_temp_str = gpu_temp
+        .map(|gpu_temp| format_temperature(gpu_temp, config))
+        .unwrap_or_default();
+    let cpu_freq = if config.display.show_cpu_freq && !state.cpu_freq_missing {
+        let cpu_freq = stats_provider.cpu_frequency();
+        if cpu_freq.is_none() {
+            warn!("CPU frequency not available, not reading it again");
+            state.cpu_freq_missing = true;
+        }
+        cpu_freq
+    } else {
+        None
+    };
+    let cpu_freq_str = cpu_freq.map(format_frequency).unwrap_or_default();
+    let cpu_usage = stats_provider.cpu_usage();
+    let cpu_usage_shown = average_cpu_usage(cpu_usage, config.display.cpu_average_samples, state);
+    let decimals = config.display.decimals;
+    let cpu_usage_str = format!("{:.*}", decimals, cpu_usage_shown);
+    let cpu_per_core: Vec<f32> = stats_provider.cpu_per_core();
+    let (used_memory, total_memory) = stats_provider.memory();
+    let ram_usage = used_memory as f64 / total_memory as f64 * 100.0;
+    let (ram_usage_str, ram_unit) = match config.display.mem_format.as_str() {
+        "binary" => format_memory(used_memory, total_memory, 1024),
+        "decimal" => format_memory(used_memory, total_memory, 1000),
+        _ => (format!("{:.*}", decimals, ram_usage), "%"),
+    };
+    let swap_usage = stats_provider.swap_usage();
+    let swap_usage_str = match swap_usage {
+        Some(swap_usage) => format!("{:.*}%", decimals, swap_usage),
+        None => "off".to_string(),
+    };
+    let swap_warning = swap_usage.is_some_and(|usage| usage >= config.memory.swap_warn_threshold);
+    let uptime = stats_provider.uptime();
+    let uptime_str = format_uptime(uptime);
+    let load_avg = stats_provider.load_average();
+    // Per-core load makes 1.0 mean "fully busy" regardless of the core count.
+    let load_divisor = if config.display.normalize_load {
+        cpu_per_core.len().max(1) as f64
+    } else {
+        1.0
+    };
+    let load_avg_str = format!(
+        "{:.1} {:.1} {:.1}",
+        load_avg[0] / load_divisor,
+        load_avg[1] / load_divisor,
+        load_avg[2] / load_divisor
+    );
+    let (disk_used, disk_total) = stats_provider.disk_space(&config.disk.mount_point);
+    let disk_usage = if disk_total > 0 {
+        disk_used as f64 / disk_total as f64 * 100.0
+    } else {
+        0.0
+    };
+    let disk_usage_str = format!("{:.*}", decimals, disk_usage);
+    let disk_warning = disk_usage >= config.disk.warn_threshold;
+    let throughput =
+        network_monitor.throughput(config.network.net_interface.as_deref(), &ip_address);
+    let (net_interface, net_rx, net_tx) = throughput
+        .map(|t| (t.interface, t.rx, t.tx))
+        .unwrap_or_default();
+    let net_rx_str = format_rate(net_rx);
+    let net_tx_str = format_rate(net_tx);
+    let gateway = stats_provider
+        .gateway(ip_version)
+        .map(|gateway| gateway.to_string());
+    let throttle_status = if state.throttle_missing {
+        Vec::new()
+    } else {
+        stats_provider.throttle_status().unwrap_or_else(|| {
+            info!("Throttling status not available, not reading it again");
+            state.throttle_missing = true;
+            Vec::new()
+        })
+    };
+    if !throttle_status.is_empty() {
+        debug!("Throttling active: {}", throttle_status.join(", "));
+    }
+    let fan_rpm = fan_controller.and_then(FanController::read_rpm);
+    if let Some(rpm) = fan_rpm {
+        debug!("Fan RPM: {}", rpm);
+    }
+    let summary = (!config.display.summary_fields.is_empty()).then(|| {
+        let temp_unit = if config.display.use_fahrenheit() {
+            "F"
+        } else {
+            "C"
+        };
+        let values: Vec<String> = config
+            .display
+            .summary_fields
+            .iter()
+            .filter_map(|field| match field.as_str() {
+                "temp" => Some(format!("{}{}", cpu_temp_str, temp_unit)),
+                "cpu" => Some(format!("{}%", cpu_usage_str)),
+                "ram" => Some(format!("{}{}", ram_usage_str, ram_unit)),
+                "rpm" => fan_rpm.map(|rpm| format!("{}RPM", rpm)),
+                _ => None,
+            })
+            .collect();
+        values.join(&config.display.summary_separator)
+    });
+    let power = power_sensor.and_then(|sensor| match sensor.read() {
+        Ok(power) => Some(power),
+        Err(e) => {
+            debug!("Failed to read power sensor: {}", e);
+            None
+        }
+    });
 
     SystemStats {
         ip_address,
         cpu_usage,
+        cpu_usage_shown,
+        cpu_usage_str,
+        cpu_per_core,
         cpu_temp,
         cpu_temp_str,
         ram_usage,
+        ram_usage_str,
+        ram_unit,
+        swap_usage,
+        swap_usage_str,
+        swap_warning,
         hostname,
+        fan_rpm,
+        uptime,
+        uptime_str,
+        load_avg,
+        load_avg_str,
+        disk_usage,
+        disk_usage_str,
+        disk_warning,
+        disk_used,
+        disk_total,
+        net_interface,
+        net_rx,
+        net_rx_str,
+        net_tx,
+        net_tx_str,
+        gateway,
+        throttle_status,
+        gpu_temp,
+        gpu_temp_str,
+        cpu_freq,
+        cpu_freq_str,
+        power_voltage: power.as_ref().map(|power| power.voltage),
+        power_current: power.as_ref().map(|power| power.current),
+        power_watts: power.as_ref().map(|power| power.watts),
+        temp_history,
+        qr_text,
+        time: Local::now().format(&config.display.time_format).to_string(),
+        summary,
+    }
+}
+
+/// Mean of the last `samples` CPU usage readings including `cpu_usage`.
+fn average_cpu_usage(cpu_usage: f32, samples: usize, state: &mut AppState) -> f32 {
+    state.cpu_samples.push_back(cpu_usage);
+    while state.cpu_samples.len() > samples {
+        state.cpu_samples.pop_front();
+    }
+    state.cpu_samples.iter().sum::<f32>() / state.cpu_samples.len() as f32
+}
+
+/// Appends `cpu_temp` to the sparkline history once per `interval`, keeping
+/// the latest `samples` readings.
+fn record_temp_sample(cpu_temp: f32, samples: usize, interval: Duration, state: &mut AppState) {
+    let now = Instant::now();
+    if state
+        .last_temp_sample
+        .is_some_and(|last| now.duration_since(last) < interval)
+    {
+        return;
+    }
+    state.last_temp_sample = Some(now);
+    state.temp_history.push_back(cpu_temp);
+    while state.temp_history.len() > samples {
+        state.temp_history.pop_front();
+    }
+}
+
+/// Puts the fan in its `fan.boot_behavior` state before the first loop
+/// iteration. "auto" starts it right away when the Pi comes back hot, e.g.
+/// after a warm reboot.
+fn start_fan(
+    fan_controller: &mut FanController,
+    config: &Config,
+    stats_provider: &dyn StatsProvider,
+) -> Result<(), Box<dyn Error>> {
+    match config.fan.boot_behavior.as_str() {
+        "on" => {
+            info!("Turning fan on at startup");
+            fan_controller.fan_on()
+        }
+        "auto" => {
+            let Some(temp) = stats_provider.cpu_temperature(&config.temperature) else {
+                warn!("No CPU temperature at startup, turning fan on");
+                return fan_controller.fan_on();
+            };
+            fan_controller.force_off()?;
+            fan_controller.set_speed(temp)?;
+            info!(
+                "CPU at {:.1}°C at startup, fan {}",
+                temp,
+                if fan_controller.is_running {
+                    "on"
+                } else {
+                    "off"
+                }
+            );
+            Ok(())
+        }
+        _ => fan_controller.fan_off(),
     }
 }
 
 fn handle_fan_control(
     fan_controller: &mut FanController,
     cpu_temp: f32,
+    fan_config: &FanConfig,
+    mqtt_publisher: Option<&MqttPublisher>,
 ) -> Result<(), Box<dyn Error>> {
     trace!(
         "Checking fan controller. Fan running: {}",
@@ -244,63 +1661,713 @@ fn handle_fan_control(
     );
     trace!("CPU Temp: {}", cpu_temp);
 
-    if fan_controller.is_running {
-        if cpu_temp <= fan_controller.temp_off {
-            fan_controller.fan_off()?;
+    let was_running = fan_controller.is_running;
+    fan_controller.set_speed(cpu_temp)?;
+    trace!("Fan duty cycle: {}%", fan_controller.duty());
+
+    if fan_controller.is_running != was_running {
+        let state = if fan_controller.is_running {
+            "ON"
+        } else {
+            "OFF"
+        };
+        if fan_controller.uses_curve() {
+            info!(
+                "Fan {} at {:.1}°C (duty cycle {}%)",
+                state,
+                cpu_temp,
+                fan_controller.duty()
+            );
+        } else {
+            let threshold = if fan_controller.is_running {
+                fan_controller.temp_on
+            } else {
+                fan_controller.temp_off
+            };
+            info!(
+                "Fan {} at {:.1}°C (threshold {:.1})",
+                state, cpu_temp, threshold
+            );
+        }
+        if let Some(mqtt_publisher) = mqtt_publisher {
+            mqtt_publisher.publish_fan(fan_controller.is_running, fan_controller.duty());
+        }
+        run_fan_hook(fan_config, fan_controller.is_running, Some(cpu_temp));
+    }
+    Ok(())
+}
+
+/// Whether a `SIGUSR1` max cool run is still going; logs its end.
+fn max_cool_active(now: Instant, state: &mut AppState) -> bool {
+    match state.max_cool_until {
+        Some(until) if now < until => true,
+        Some(_) => {
+            info!("Max cool run ended, back to automatic fan control");
+            state.max_cool_until = None;
+            false
         }
-    } else if cpu_temp >= fan_controller.temp_on {
+        None => false,
+    }
+}
+
+/// Forces the fan on or off for a control server override or a max cool
+/// run. The minimum run time does not apply.
+fn handle_fan_override(
+    fan_controller: &mut FanController,
+    on: bool,
+    fan_config: &FanConfig,
+    mqtt_publisher: Option<&MqttPublisher>,
+) -> Result<(), Box<dyn Error>> {
+    if on == fan_controller.is_running {
+        return Ok(());
+    }
+    if on {
+        info!("Manual override: fan on");
         fan_controller.fan_on()?;
+    } else {
+        info!("Manual override: fan off");
+        fan_controller.force_off()?;
+    }
+    if let Some(mqtt_publisher) = mqtt_publisher {
+        mqtt_publisher.publish_fan(fan_controller.is_running, fan_controller.duty());
     }
+    run_fan_hook(fan_config, fan_controller.is_running, None);
     Ok(())
 }
 
-fn get_ip_address() -> String {
-    Command::new("hostname")
-        .arg("-I")
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                String::from_utf8(output.stdout)
-                    .ok()
-                    .and_then(|s| s.split_whitespace().next().map(str::to_string))
-            } else {
-                None
-            }
-        })
-        .unwrap_or_else(|| "0.0.0.0".to_string())
-        .trim()
-        .to_string()
+/// Starts `on_fan_on` or `on_fan_off` without waiting for it, so a slow
+/// command cannot hold up the loop. The command gets `FAN_STATE` (`on` or
+/// `off`) and, unless the change was manual, `CPU_TEMP` in its environment.
+fn run_fan_hook(fan_config: &FanConfig, running: bool, cpu_temp: Option<f32>) {
+    let (state, hook) = if running {
+        ("on", &fan_config.on_fan_on)
+    } else {
+        ("off", &fan_config.on_fan_off)
+    };
+    let Some(hook) = hook else {
+        return;
+    };
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(hook).env("FAN_STATE", state);
+    if let Some(cpu_temp) = cpu_temp {
+        command.env("CPU_TEMP", format!("{:.1}", cpu_temp));
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to run fan {} command: {}", state, e);
+            return;
+        }
+    };
+    debug!("Started fan {} command '{}'", state, hook);
+    // Reaped on its own thread, so finished commands do not linger as zombies.
+    thread::spawn(move || match child.wait() {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("Fan {} command exited with {}", state, status),
+        Err(e) => warn!("Failed to wait for fan {} command: {}", state, e),
+    });
 }
 
-fn get_hostname() -> String {
-    Command::new("hostname")
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                String::from_utf8(output.stdout).ok()
-            } else {
+/// Shuts the Pi down once the CPU has been at or above `temp_critical` for
+/// `critical_readings` consecutive refreshes. The display is only updated
+/// after that, so the warning stays visible while the system goes down.
+fn handle_critical_temperature(
+    cpu_temp: f32,
+    fan_config: &FanConfig,
+    simulated: bool,
+    state: &mut AppState,
+    displays: &mut [PoeDisplay],
+) {
+    let Some(temp_critical) = fan_config.temp_critical else {
+        return;
+    };
+    if cpu_temp < temp_critical {
+        if state.critical_readings > 0 {
+            info!(
+                "CPU temperature back below critical threshold ({:.1}°C)",
+                cpu_temp
+            );
+        }
+        state.critical_readings = 0;
+        return;
+    }
+
+    state.critical_readings += 1;
+    warn!(
+        "CPU temperature {:.1}°C at or above critical threshold {:.1}°C ({}/{})",
+        cpu_temp, temp_critical, state.critical_readings, fan_config.critical_readings
+    );
+    if state.critical_readings < fan_config.critical_readings || state.critical_shutdown_started {
+        return;
+    }
+
+    error!(
+        "CPU temperature critical for {} readings. Running '{}'",
+        state.critical_readings, fan_config.critical_command
+    );
+    state.critical_shutdown_started = true;
+    for poe_disp in displays.iter_mut() {
+        if let Err(e) = poe_disp.show_alert("TEMP CRITICAL", "SHUTTING DOWN") {
+            warn!("Failed to show critical temperature warning: {:?}", e);
+        }
+    }
+    if simulated {
+        warn!("Simulating, the critical temperature command is not run");
+        return;
+    }
+    match Command::new("sh")
+        .arg("-c")
+        .arg(&fan_config.critical_command)
+        .status()
+    {
+        Ok(status) if status.success() => {}
+        Ok(status) => error!("Critical temperature command exited with {}", status),
+        Err(e) => error!("Failed to run critical temperature command: {}", e),
+    }
+}
+
+const UNKNOWN_IP: &str = "0.0.0.0";
+const IP_WAIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The IP address, waiting up to `network.startup_delay_secs` for one to
+/// show up, so the first frames after boot do not show `0.0.0.0`.
+fn wait_for_ip_address(
+    config: &Config,
+    version: IpVersion,
+    stats_provider: &dyn StatsProvider,
+) -> String {
+    let interface = config.network.ip_interface.as_deref();
+    let skip = config.ip_skip_ranges();
+    let max_wait = Duration::from_secs(config.network.startup_delay_secs);
+    let start = Instant::now();
+    let mut ip_address = stats_provider.ip_address(interface, version, &skip);
+    if ip_address == UNKNOWN_IP && !max_wait.is_zero() {
+        info!(
+            "No IP address yet, waiting up to {}s for one",
+            max_wait.as_secs()
+        );
+        while ip_address == UNKNOWN_IP && start.elapsed() < max_wait {
+            thread::sleep(IP_WAIT_POLL_INTERVAL.min(max_wait.saturating_sub(start.elapsed())));
+            ip_address = stats_provider.ip_address(interface, version, &skip);
+        }
+        if ip_address == UNKNOWN_IP {
+            warn!(
+                "Still no IP address after {}s, starting without one",
+                max_wait.as_secs()
+            );
+        } else {
+            info!(
+                "Got IP address {} after {:.0}s",
+                ip_address,
+                start.elapsed().as_secs_f32()
+            );
+        }
+    }
+    ip_address
+}
+
+/// `display.qr_template` with `{ip}` and `{hostname}` filled in. `None` when
+/// no template is set or it needs the IP address and there is none.
+fn qr_text(config: &Config, ip_address: &str, hostname: &str) -> Option<String> {
+    let template = config.display.qr_template.as_deref()?;
+    if template.contains("{ip}") && ip_address == UNKNOWN_IP {
+        return None;
+    }
+    Some(
+        template
+            .replace("{ip}", ip_address)
+            .replace("{hostname}", hostname),
+    )
+}
+
+/// Exponential moving average of the CPU temperature. `factor` is the weight
+/// of the previous value, so 0.0 passes readings through unchanged.
+fn smooth_temperature(reading: f32, previous: Option<f32>, factor: f32) -> f32 {
+    match previous {
+        Some(previous) => factor * previous + (1.0 - factor) * reading,
+        None => reading,
+    }
+}
+
+/// Readings outside this range in Celsius come from a sensor with a
+/// different scale than `temp_divisor`, not from the CPU.
+const PLAUSIBLE_TEMP_RANGE: std::ops::RangeInclusive<f32> = -20.0..=150.0;
+
+fn read_cpu_temperature(temp_config: &TemperatureConfig) -> Option<f32> {
+    match temp_config.temp_source.as_str() {
+        // `measure_temp` reports the SoC temperature, CPU and GPU share it.
+        "vcgencmd" => get_gpu_temperature(),
+        "command" => temp_config
+            .temp_command
+            .as_deref()
+            .and_then(run_temp_command),
+        _ => read_sensor_files(temp_config),
+    }
+}
+
+/// Reads every configured sensor and combines the readings by maximum or
+/// average. Unreadable sensors and implausible readings are skipped; `None`
+/// when none is left.
+fn read_sensor_files(temp_config: &TemperatureConfig) -> Option<f32> {
+    let readings: Vec<f32> = temp_config
+        .sensor_paths()
+        .filter_map(|path| match fs::read_to_string(path) {
+            Ok(contents) => {
+                let Some(raw) = parse_sensor_value(&contents) else {
+                    log::warn!(
+                        "Ignoring unexpected temperature {:?} from {}",
+                        contents, path
+                    );
+                    return None;
+                };
+                let temp = raw as f32 / temp_config.temp_divisor;
+                if PLAUSIBLE_TEMP_RANGE.contains(&temp) {
+                    Some(temp)
+                } else {
+                    log::warn!(
+                        "Ignoring implausible temperature {:.1}°C from {}; check temperature.temp_divisor",
+                        temp, path
+                    );
+                    None
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to read CPU temperature from {}: {}", path, e);
                 None
             }
         })
-        .unwrap_or_else(|| "UNKNOWN".to_string())
-        .trim()
-        .to_string()
+        .collect();
+    if readings.is_empty() {
+        return None;
+    }
+    if temp_config.combine == "average" {
+        Some(readings.iter().sum::<f32>() / readings.len() as f32)
+    } else {
+        Some(readings.iter().copied().fold(f32::MIN, f32::max))
+    }
 }
 
-fn get_cpu_temperature() -> f32 {
-    match fs::read_to_string("/sys/class/thermal/thermal_zone0/temp") {
-        Ok(contents) => contents.trim().parse::<f32>().unwrap_or(0.0) / 1000.0,
+/// Runs `temperature.temp_command` and reads degrees Celsius, e.g. `48.3`,
+/// from its output.
+fn run_temp_command(command: &str) -> Option<f32> {
+    let output = match Command::new("sh").arg("-c").arg(command).output() {
+        Ok(output) => output,
         Err(e) => {
-            log::warn!("Failed to read CPU temperature: {}", e);
-            0.0
+            log::warn!("Failed to run temperature command: {}", e);
+            return None;
+        }
+    };
+    if !output.status.success() {
+        log::warn!("Temperature command exited with {}", output.status);
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.trim().parse::<f32>() {
+        Ok(temp) if PLAUSIBLE_TEMP_RANGE.contains(&temp) => Some(temp),
+        _ => {
+            log::warn!(
+                "Ignoring unexpected temperature command output {:?}",
+                stdout
+            );
+            None
         }
     }
 }
 
-fn get_ram_usage(sys: &System) -> f64 {
-    let total_memory = sys.total_memory();
-    let used_memory = sys.used_memory();
-    (used_memory as f64 / total_memory as f64) * 100.0
+/// Integer reading of a sensor file such as `48312\n`. Whitespace, a BOM or
+/// other characters around the number are dropped; `None` when there is no
+/// number, so a garbled file is not taken for 0°C.
+fn parse_sensor_value(contents: &str) -> Option<i64> {
+    let start = contents.find(|c: char| c.is_ascii_digit())?;
+    let end = contents[start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(contents.len(), |len| start + len);
+    let negative = contents[..start].ends_with('-');
+    let value: i64 = contents[start..end].parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Formats an uptime as `2d 4h 13m`. From 100 days on the minutes are
+/// dropped so the value keeps fitting on one display line.
+fn format_uptime(seconds: u64) -> String {
+    let days = seconds / 86_400;
+    let hours = seconds % 86_400 / 3_600;
+    let minutes = seconds % 3_600 / 60;
+    if days >= 100 {
+        format!("{}d {}h", days, hours)
+    } else {
+        format!("{}d {}h {}m", days, hours, minutes)
+    }
+}
+
+/// Formats a clock in MHz as e.g. `600MHz` or `1.80GHz`.
+fn format_frequency(mhz: u32) -> String {
+    if mhz >= 1000 {
+        format!("{:.2}GHz", mhz as f64 / 1000.0)
+    } else {
+        format!("{}MHz", mhz)
+    }
+}
+
+/// Formats a byte rate as `12.3KB/s` or `1.2MB/s`.
+fn format_rate(bytes_per_sec: f64) -> String {
+    const KIB: f64 = 1024.0;
+    if bytes_per_sec >= KIB * KIB {
+        format!("{:.1}MB/s", bytes_per_sec / (KIB * KIB))
+    } else {
+        format!("{:.1}KB/s", bytes_per_sec / KIB)
+    }
+}
+
+fn celsius_to_fahrenheit(celsius: f32) -> f32 {
+    celsius * 9.0 / 5.0 + 32.0
+}
+
+/// Formats a temperature in the configured display unit, without the unit.
+fn format_temperature(celsius: f32, config: &Config) -> String {
+    if config.display.use_fahrenheit() {
+        format!(
+            "{:.*}",
+            config.display.decimals,
+            celsius_to_fahrenheit(celsius)
+        )
+    } else {
+        format!("{:.*}", config.display.decimals, celsius)
+    }
+}
+
+/// GPU temperature from `vcgencmd measure_temp`, which prints e.g.
+/// `temp=48.3'C`. `None` when the tool is missing or fails.
+fn get_gpu_temperature() -> Option<f32> {
+    let output = Command::new("vcgencmd").arg("measure_temp").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .trim()
+        .strip_prefix("temp=")?
+        .trim_end_matches("'C")
+        .parse()
+        .ok()
+}
+
+/// Formats used/total memory as e.g. `1.2/3.8` GB, or in MB when there is
+/// less than 1 GB. `base` is 1024 for binary and 1000 for decimal units.
+fn format_memory(used: u64, total: u64, base: u64) -> (String, &'static str) {
+    let mega = base * base;
+    let (divisor, unit) = if total >= mega * base {
+        (mega * base, "G")
+    } else {
+        (mega, "M")
+    };
+    let used = used as f64 / divisor as f64;
+    let total = total as f64 / divisor as f64;
+    // Whole numbers from 10 up keep e.g. `12/16` short enough for the row.
+    let precision = if unit == "G" && total < 10.0 { 1 } else { 0 };
+    (
+        format!("{:.*}/{:.*}", precision, used, precision, total),
+        unit,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stats_provider::MockProvider;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn test_state() -> AppState {
+        AppState::new(
+            vec![Page::Stats],
+            "raspberrypi".to_string(),
+            "192.168.1.42".to_string(),
+            1,
+        )
+    }
+
+    fn gather(provider: &mut MockProvider, config: &Config, state: &mut AppState) -> SystemStats {
+        gather_stats(
+            provider,
+            &mut NetworkMonitor::new(),
+            None,
+            None,
+            config,
+            IpVersion::Auto,
+            state,
+        )
+    }
+
+    fn fan_config(temp_on: f32, temp_off: f32) -> FanConfig {
+        let mut fan_config = Config::default().fan;
+        fan_config.temp_on = Some(temp_on);
+        fan_config.temp_off = Some(temp_off);
+        fan_config.hysteresis = None;
+        fan_config.min_run_secs = 0;
+        fan_config
+    }
+
+    #[test]
+    fn gather_stats_formats_the_readings() {
+        let mut config = Config::default();
+        config.display.show_cpu_freq = true;
+        let mut provider = MockProvider {
+            swap_usage: Some(12.5),
+            ..MockProvider::default()
+        };
+        let stats = gather(&mut provider, &config, &mut test_state());
+        assert_eq!(stats.cpu_temp, 48.2);
+        assert_eq!(stats.cpu_temp_str, "48.2");
+        assert_eq!(stats.cpu_usage_str, "12.3");
+        assert_eq!(stats.ram_usage, 25.0);
+        assert_eq!(
+            (stats.ram_usage_str.as_str(), stats.ram_unit),
+            ("25.0", "%")
+        );
+        assert_eq!(stats.swap_usage_str, "12.5%");
+        assert_eq!(stats.disk_usage_str, "25.0");
+        assert_eq!(stats.cpu_freq_str, "1.80GHz");
+        assert_eq!(stats.gateway.as_deref(), Some("192.168.1.1"));
+        assert_eq!(stats.hostname, "raspberrypi");
+    }
+
+    #[test]
+    fn gather_stats_reads_the_host_values_from_the_provider() {
+        let mut config = Config::default();
+        config.temperature.gpu = true;
+        config.display.normalize_load = true;
+        let mut provider = MockProvider {
+            throttle_status: Some(vec!["Undervoltage".to_string()]),
+            ..MockProvider::default()
+        };
+        let stats = gather(&mut provider, &config, &mut test_state());
+        assert_eq!(stats.gpu_temp_str, "47.1");
+        assert_eq!(stats.throttle_status, ["Undervoltage"]);
+        assert_eq!(stats.uptime_str, "3d 4h 5m");
+        // Divided by the mock's four cores.
+        assert_eq!(stats.load_avg_str, "0.1 0.1 0.1");
+    }
+
+    #[test]
+    fn gather_stats_stops_reading_a_missing_throttle_status() {
+        let config = Config::default();
+        let mut state = test_state();
+        let mut provider = MockProvider {
+            throttle_status: None,
+            ..MockProvider::default()
+        };
+        let stats = gather(&mut provider, &config, &mut state);
+        assert!(stats.throttle_status.is_empty());
+        assert!(state.throttle_missing);
+
+        provider.throttle_status = Some(vec!["Throttled".to_string()]);
+        let stats = gather(&mut provider, &config, &mut state);
+        assert!(stats.throttle_status.is_empty());
+    }
+
+    #[test]
+    fn gather_stats_follows_the_display_settings() {
+        let mut config = Config::default();
+        config.display.temp_unit = "F".to_string();
+        config.display.decimals = 0;
+        config.display.mem_format = "binary".to_string();
+        config.display.summary_fields = vec!["temp".to_string(), "cpu".to_string()];
+        let mut provider = MockProvider {
+            cpu_temp: Some(50.0),
+            memory: (3 * GIB / 2, 4 * GIB),
+            ..MockProvider::default()
+        };
+        let stats = gather(&mut provider, &config, &mut test_state());
+        assert_eq!(stats.cpu_temp, 50.0);
+        assert_eq!(stats.cpu_temp_str, "122");
+        assert_eq!(stats.cpu_usage_str, "12");
+        assert_eq!(
+            (stats.ram_usage_str.as_str(), stats.ram_unit),
+            ("1.5/4.0", "G")
+        );
+        assert_eq!(stats.swap_usage_str, "off");
+        assert_eq!(stats.summary.as_deref(), Some("122F 12%"));
+    }
+
+    #[test]
+    fn gather_stats_averages_the_cpu_usage() {
+        let mut config = Config::default();
+        config.display.cpu_average_samples = 2;
+        let mut state = test_state();
+        let mut provider = MockProvider {
+            cpu_usage: 10.0,
+            ..MockProvider::default()
+        };
+        gather(&mut provider, &config, &mut state);
+        provider.cpu_usage = 30.0;
+        let stats = gather(&mut provider, &config, &mut state);
+        assert_eq!(stats.cpu_usage, 30.0);
+        assert_eq!(stats.cpu_usage_str, "20.0");
+    }
+
+    #[test]
+    fn gather_stats_keeps_the_last_temperature_when_the_reading_fails() {
+        let config = Config::default();
+        let mut state = test_state();
+        let mut provider = MockProvider {
+            cpu_temp: None,
+            ..MockProvider::default()
+        };
+        let stats = gather(&mut provider, &config, &mut state);
+        assert_eq!(stats.cpu_temp_str, "--");
+        assert!(state.temp_missing);
+
+        provider.cpu_temp = Some(55.0);
+        gather(&mut provider, &config, &mut state);
+        assert!(!state.temp_missing);
+
+        provider.cpu_temp = None;
+        let stats = gather(&mut provider, &config, &mut state);
+        assert_eq!(stats.cpu_temp, 55.0);
+        assert_eq!(stats.cpu_temp_str, "55.0");
+        assert!(state.temp_missing);
+    }
+
+    #[test]
+    fn fan_turns_on_at_temp_on() {
+        let fan_config = fan_config(60.0, 50.0);
+        let mut fan = FanController::simulated(&fan_config).unwrap();
+        handle_fan_control(&mut fan, 59.9, &fan_config, None).unwrap();
+        assert!(!fan.is_running);
+        handle_fan_control(&mut fan, 60.0, &fan_config, None).unwrap();
+        assert!(fan.is_running);
+        assert_eq!(fan.duty(), 100);
+    }
+
+    #[test]
+    fn fan_keeps_running_until_temp_off() {
+        let fan_config = fan_config(60.0, 50.0);
+        let mut fan = FanController::simulated(&fan_config).unwrap();
+        handle_fan_control(&mut fan, 65.0, &fan_config, None).unwrap();
+        for temp in [59.0, 55.0, 50.1] {
+            handle_fan_control(&mut fan, temp, &fan_config, None).unwrap();
+            assert!(fan.is_running, "fan stopped at {}°C", temp);
+        }
+        handle_fan_control(&mut fan, 50.0, &fan_config, None).unwrap();
+        assert!(!fan.is_running);
+        assert_eq!(fan.duty(), 0);
+        // Below temp_on the stopped fan stays off again.
+        handle_fan_control(&mut fan, 55.0, &fan_config, None).unwrap();
+        assert!(!fan.is_running);
+    }
+
+    #[test]
+    fn fan_hysteresis_sets_temp_off_below_temp_on() {
+        let mut fan_config = fan_config(60.0, 0.0);
+        fan_config.temp_off = None;
+        fan_config.hysteresis = Some(5.0);
+        let mut fan = FanController::simulated(&fan_config).unwrap();
+        handle_fan_control(&mut fan, 60.0, &fan_config, None).unwrap();
+        handle_fan_control(&mut fan, 55.1, &fan_config, None).unwrap();
+        assert!(fan.is_running);
+        handle_fan_control(&mut fan, 55.0, &fan_config, None).unwrap();
+        assert!(!fan.is_running);
+    }
+
+    #[test]
+    fn fan_min_run_time_delays_the_stop() {
+        let mut fan_config = fan_config(60.0, 50.0);
+        fan_config.min_run_secs = 3600;
+        let mut fan = FanController::simulated(&fan_config).unwrap();
+        handle_fan_control(&mut fan, 60.0, &fan_config, None).unwrap();
+        handle_fan_control(&mut fan, 40.0, &fan_config, None).unwrap();
+        assert!(fan.is_running);
+    }
+
+    #[test]
+    fn parse_sensor_value_reads_millidegrees() {
+        assert_eq!(parse_sensor_value("48312\n"), Some(48312));
+    }
+
+    #[test]
+    fn parse_sensor_value_skips_bom() {
+        assert_eq!(parse_sensor_value("\u{feff}48312\n"), Some(48312));
+    }
+
+    #[test]
+    fn parse_sensor_value_ignores_trailing_garbage() {
+        assert_eq!(parse_sensor_value("48312 mC\r\n"), Some(48312));
+        assert_eq!(parse_sensor_value("48312\0\0"), Some(48312));
+    }
+
+    #[test]
+    fn parse_sensor_value_rejects_empty_input() {
+        assert_eq!(parse_sensor_value(""), None);
+        assert_eq!(parse_sensor_value(" \n"), None);
+    }
+
+    #[test]
+    fn parse_sensor_value_rejects_non_numeric_input() {
+        assert_eq!(parse_sensor_value("N/A\n"), None);
+        assert_eq!(parse_sensor_value("-"), None);
+    }
+
+    #[test]
+    fn parse_sensor_value_keeps_the_sign() {
+        assert_eq!(parse_sensor_value("-5250\n"), Some(-5250));
+        assert_eq!(parse_sensor_value("\u{feff}-5250"), Some(-5250));
+    }
+
+    /// Offsets of `count` random shifts within `range`, from `seed`.
+    fn random_shifts(seed: u64, range: Point, count: usize) -> Vec<Point> {
+        let mut state = test_state();
+        state.shift_rng = shift_seed(Some(seed));
+        let interval = Duration::from_secs(60);
+        (1..=count as u32)
+            .map(|step| {
+                let now = state.last_shift_time + interval * step;
+                update_pixel_shift(now, interval, &[Point::zero()], Some(range), &mut state);
+                state.shift_offset
+            })
+            .collect()
+    }
+
+    #[test]
+    fn seeded_random_shift_repeats() {
+        let range = Point::new(3, 2);
+        assert_eq!(random_shifts(42, range, 20), random_shifts(42, range, 20));
+        assert_ne!(random_shifts(42, range, 20), random_shifts(43, range, 20));
+    }
+
+    #[test]
+    fn random_shift_stays_within_the_range() {
+        let range = Point::new(3, 2);
+        let shifts = random_shifts(7, range, 200);
+        assert!(shifts
+            .iter()
+            .all(|shift| (0..=3).contains(&shift.x) && (0..=2).contains(&shift.y)));
+        // Every offset of the range is used.
+        for x in 0..=3 {
+            for y in 0..=2 {
+                assert!(shifts.contains(&Point::new(x, y)), "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn random_shift_waits_for_the_interval() {
+        let mut state = test_state();
+        let interval = Duration::from_secs(60);
+        let now = state.last_shift_time + interval / 2;
+        update_pixel_shift(
+            now,
+            interval,
+            &[Point::zero()],
+            Some(Point::new(3, 3)),
+            &mut state,
+        );
+        assert_eq!(state.shift_offset, Point::zero());
+    }
+
+    #[test]
+    fn shift_seed_never_starts_at_zero() {
+        assert_ne!(shift_seed(Some(0)), 0);
+        assert_eq!(shift_seed(Some(5)), shift_seed(Some(5)));
+    }
 }
@@ -0,0 +1,156 @@
+use crate::http::{read_line_capped, request_reader};
+use crate::SystemStats;
+use log::{debug, info, warn};
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Serves the latest stats in the Prometheus text format at `/metrics`.
+pub struct MetricsServer {
+    snapshot: Arc<Mutex<String>>,
+}
+
+impl MetricsServer {
+    /// Binds the metrics port and spawns the server thread. Returns `None` if
+    /// the port cannot be bound, so the monitor keeps running without metrics.
+    pub fn start(port: u16) -> Option<Self> {
+        let listener = match TcpListener::bind(("0.0.0.0", port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!(
+                    "Failed to bind metrics server on port {}: {}. Continuing without metrics.",
+                    port, e
+                );
+                return None;
+            }
+        };
+        info!("Metrics server listening on port {}", port);
+
+        let snapshot = Arc::new(Mutex::new(String::new()));
+        let server_snapshot = Arc::clone(&snapshot);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(e) = handle_connection(stream, &server_snapshot) {
+                            debug!("Metrics connection error: {}", e);
+                        }
+                    }
+                    Err(e) => debug!("Failed to accept metrics connection: {}", e),
+                }
+            }
+        });
+
+        Some(MetricsServer { snapshot })
+    }
+
+    pub fn update(&self, stats: &SystemStats, fan_running: bool) {
+        let mut body = format!(
+            "# HELP rustberry_cpu_usage CPU usage in percent.\n\
+             # TYPE rustberry_cpu_usage gauge\n\
+             rustberry_cpu_usage {:.1}\n\
+             # HELP rustberry_cpu_temp CPU temperature in degrees Celsius.\n\
+             # TYPE rustberry_cpu_temp gauge\n\
+             rustberry_cpu_temp {:.1}\n\
+             # HELP rustberry_ram_usage RAM usage in percent.\n\
+             # TYPE rustberry_ram_usage gauge\n\
+             rustberry_ram_usage {:.1}\n\
+             # HELP rustberry_fan_running Whether the fan is running (1) or not (0).\n\
+             # TYPE rustberry_fan_running gauge\n\
+             rustberry_fan_running {}\n",
+            stats.cpu_usage, stats.cpu_temp, stats.ram_usage, fan_running as u8
+        );
+        if let Some(power_watts) = stats.power_watts {
+            body.push_str(&format!(
+                "# HELP rustberry_power_watts Power draw measured by the INA219 in watts.\n\
+                 # TYPE rustberry_power_watts gauge\n\
+                 rustberry_power_watts {:.2}\n",
+                power_watts
+            ));
+        }
+        if let Some(gpu_temp) = stats.gpu_temp {
+            body.push_str(&format!(
+                "# HELP rustberry_gpu_temp GPU temperature in degrees Celsius.\n\
+                 # TYPE rustberry_gpu_temp gauge\n\
+                 rustberry_gpu_temp {:.1}\n",
+                gpu_temp
+            ));
+        }
+        *self.snapshot.lock().unwrap() = body;
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, snapshot: &Mutex<String>) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    read_line_capped(&mut request_reader(&stream)?, &mut request_line)?;
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let response = if path == "/metrics" {
+        let body = snapshot.lock().unwrap().clone();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exposition(stats: &SystemStats, fan_running: bool) -> String {
+        let server = MetricsServer {
+            snapshot: Arc::new(Mutex::new(String::new())),
+        };
+        server.update(stats, fan_running);
+        let body = server.snapshot.lock().unwrap().clone();
+        body
+    }
+
+    fn gauges(body: &str) -> Vec<&str> {
+        body.lines().filter(|line| !line.starts_with('#')).collect()
+    }
+
+    #[test]
+    fn exposes_the_gauges() {
+        let stats = SystemStats {
+            cpu_usage: 12.34,
+            cpu_temp: 48.25,
+            ram_usage: 56.7,
+            ..Default::default()
+        };
+        let body = exposition(&stats, true);
+        assert_eq!(
+            gauges(&body),
+            [
+                "rustberry_cpu_usage 12.3",
+                "rustberry_cpu_temp 48.2",
+                "rustberry_ram_usage 56.7",
+                "rustberry_fan_running 1",
+            ]
+        );
+        assert!(body.contains("# TYPE rustberry_cpu_temp gauge\n"));
+        assert!(body.ends_with('\n'));
+    }
+
+    #[test]
+    fn exposes_power_and_gpu_when_measured() {
+        let stats = SystemStats {
+            power_watts: Some(4.567),
+            gpu_temp: Some(47.14),
+            ..Default::default()
+        };
+        let body = exposition(&stats, false);
+        let gauges = gauges(&body);
+        assert!(gauges.contains(&"rustberry_fan_running 0"));
+        assert!(gauges.contains(&"rustberry_power_watts 4.57"));
+        assert!(gauges.contains(&"rustberry_gpu_temp 47.1"));
+        assert!(body.contains("# TYPE rustberry_power_watts gauge\n"));
+        assert!(body.contains("# TYPE rustberry_gpu_temp gauge\n"));
+    }
+}
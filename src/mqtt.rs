@@ -0,0 +1,210 @@
+use crate::config::MqttConfig;
+use crate::SystemStats;
+use log::{debug, info, warn};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+const CLIENT_ID: &str = "rustberry-poe-monitor";
+const KEEP_ALIVE_SECS: u16 = 60;
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_DELAY_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(60);
+
+struct Message {
+    topic: String,
+    payload: Vec<u8>,
+}
+
+/// Publishes stats to an MQTT broker (QoS 0) from a background thread.
+/// Connection failures never reach the caller; the thread keeps reconnecting
+/// and messages produced while disconnected are dropped.
+pub struct MqttPublisher {
+    topic_prefix: String,
+    sender: Sender<Message>,
+}
+
+impl MqttPublisher {
+    pub fn start(mqtt_config: &MqttConfig) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let broker = BrokerSettings {
+            host: mqtt_config.host.clone(),
+            port: mqtt_config.port,
+            username: mqtt_config.username.clone(),
+            password: mqtt_config.password.clone(),
+        };
+        info!(
+            "Publishing stats to MQTT broker {}:{}",
+            broker.host, broker.port
+        );
+        thread::spawn(move || run(broker, receiver));
+
+        MqttPublisher {
+            topic_prefix: mqtt_config.topic_prefix.trim_end_matches('/').to_string(),
+            sender,
+        }
+    }
+
+    pub fn publish_stats(&self, stats: &SystemStats) {
+        match serde_json::to_vec(stats) {
+            Ok(payload) => self.publish("stats", payload),
+            Err(e) => warn!("Failed to serialize stats for MQTT: {}", e),
+        }
+    }
+
+    pub fn publish_fan(&self, running: bool, duty: u8) {
+        let payload = serde_json::json!({ "running": running, "duty": duty });
+        self.publish("fan", payload.to_string().into_bytes());
+    }
+
+    pub fn publish_ip(&self, previous: &str, current: &str) {
+        let payload = serde_json::json!({ "previous": previous, "ip": current });
+        self.publish("ip", payload.to_string().into_bytes());
+    }
+
+    fn publish(&self, subtopic: &str, payload: Vec<u8>) {
+        let message = Message {
+            topic: format!("{}/{}", self.topic_prefix, subtopic),
+            payload,
+        };
+        if self.sender.send(message).is_err() {
+            warn!("MQTT publisher thread is not running");
+        }
+    }
+}
+
+struct BrokerSettings {
+    host: String,
+    port: u16,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+fn run(broker: BrokerSettings, receiver: Receiver<Message>) {
+    let mut delay = RECONNECT_DELAY_MIN;
+    loop {
+        match connect(&broker) {
+            Ok(stream) => {
+                info!("Connected to MQTT broker {}:{}", broker.host, broker.port);
+                delay = RECONNECT_DELAY_MIN;
+                match publish_loop(stream, &receiver) {
+                    Ok(()) => return,
+                    Err(e) => warn!("MQTT connection lost: {}", e),
+                }
+            }
+            Err(e) => warn!(
+                "Failed to connect to MQTT broker {}:{}: {}. Retrying in {}s",
+                broker.host,
+                broker.port,
+                e,
+                delay.as_secs()
+            ),
+        }
+
+        thread::sleep(delay);
+        delay = (delay * 2).min(RECONNECT_DELAY_MAX);
+        // Drop whatever piled up while disconnected; only fresh stats matter.
+        while receiver.try_recv().is_ok() {}
+    }
+}
+
+/// Forwards queued messages until the connection fails. Returns `Ok` only
+/// when the publisher has been dropped.
+fn publish_loop(mut stream: TcpStream, receiver: &Receiver<Message>) -> io::Result<()> {
+    let ping_interval = Duration::from_secs(KEEP_ALIVE_SECS as u64 / 2);
+    loop {
+        match receiver.recv_timeout(ping_interval) {
+            Ok(message) => {
+                debug!("Publishing MQTT message to {}", message.topic);
+                stream.write_all(&publish_packet(&message.topic, &message.payload))?;
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                stream.write_all(&[0xC0, 0x00])?;
+                let mut response = [0u8; 2];
+                stream.read_exact(&mut response)?;
+                if response[0] != 0xD0 {
+                    return Err(io::Error::other("unexpected response to PINGREQ"));
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+fn connect(broker: &BrokerSettings) -> io::Result<TcpStream> {
+    let mut stream = TcpStream::connect((broker.host.as_str(), broker.port))?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    stream.write_all(&connect_packet(broker))?;
+
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack)?;
+    if connack[0] != 0x20 {
+        return Err(io::Error::other("unexpected response to CONNECT"));
+    }
+    if connack[3] != 0 {
+        return Err(io::Error::other(format!(
+            "broker refused connection (return code {})",
+            connack[3]
+        )));
+    }
+    Ok(stream)
+}
+
+fn connect_packet(broker: &BrokerSettings) -> Vec<u8> {
+    // Clean session; username/password flags as configured.
+    let mut flags = 0x02;
+    if broker.username.is_some() {
+        flags |= 0x80;
+    }
+    if broker.password.is_some() {
+        flags |= 0x40;
+    }
+
+    let mut body = Vec::new();
+    write_string(&mut body, "MQTT");
+    body.push(4); // protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    write_string(&mut body, CLIENT_ID);
+    if let Some(username) = &broker.username {
+        write_string(&mut body, username);
+    }
+    if let Some(password) = &broker.password {
+        write_string(&mut body, password);
+    }
+
+    packet(0x10, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    write_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(0x30, &body)
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut remaining = body.len();
+    loop {
+        let mut byte = (remaining % 128) as u8;
+        remaining /= 128;
+        if remaining > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if remaining == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
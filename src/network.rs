@@ -0,0 +1,288 @@
+use log::warn;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::time::Instant;
+use sysinfo::Networks;
+
+struct Sample {
+    interface: String,
+    received: u64,
+    transmitted: u64,
+    time: Instant,
+}
+
+/// Computes per-interface throughput from the kernel's byte counters.
+pub struct NetworkMonitor {
+    networks: Networks,
+    last_sample: Option<Sample>,
+}
+
+/// Receive/transmit rates of one interface, in bytes per second.
+pub struct Throughput {
+    pub interface: String,
+    pub rx: f64,
+    pub tx: f64,
+}
+
+impl NetworkMonitor {
+    pub fn new() -> Self {
+        NetworkMonitor {
+            networks: Networks::new_with_refreshed_list(),
+            last_sample: None,
+        }
+    }
+
+    /// Throughput of `interface`, or of the interface holding `ip_address`
+    /// when no interface is configured. The first sample of an interface has
+    /// nothing to compare against and reports zero.
+    pub fn throughput(&mut self, interface: Option<&str>, ip_address: &str) -> Option<Throughput> {
+        self.networks.refresh(true);
+
+        let name = match interface {
+            Some(name) => name.to_string(),
+            None => self.interface_for_ip(ip_address)?,
+        };
+        let data = self.networks.list().get(&name)?;
+        let sample = Sample {
+            interface: name,
+            received: data.total_received(),
+            transmitted: data.total_transmitted(),
+            time: Instant::now(),
+        };
+
+        let (rx, tx) = match &self.last_sample {
+            Some(last) if last.interface == sample.interface => {
+                let elapsed = sample.time.duration_since(last.time).as_secs_f64();
+                if elapsed > 0.0 {
+                    (
+                        sample.received.saturating_sub(last.received) as f64 / elapsed,
+                        sample.transmitted.saturating_sub(last.transmitted) as f64 / elapsed,
+                    )
+                } else {
+                    (0.0, 0.0)
+                }
+            }
+            _ => (0.0, 0.0),
+        };
+
+        let throughput = Throughput {
+            interface: sample.interface.clone(),
+            rx,
+            tx,
+        };
+        self.last_sample = Some(sample);
+        Some(throughput)
+    }
+
+    fn interface_for_ip(&self, ip_address: &str) -> Option<String> {
+        let ip: IpAddr = ip_address.parse().ok()?;
+        self.networks
+            .list()
+            .iter()
+            .find(|(_, data)| data.ip_networks().iter().any(|net| net.addr == ip))
+            .map(|(name, _)| name.clone())
+    }
+}
+
+/// Which address family is shown on the display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IpVersion {
+    V4,
+    V6,
+    /// IPv4 when available, otherwise a global IPv6 address.
+    Auto,
+}
+
+pub fn map_ip_version_value(value: &str) -> IpVersion {
+    match value {
+        "v4" => IpVersion::V4,
+        "v6" => IpVersion::V6,
+        "auto" => IpVersion::Auto,
+        _ => {
+            warn!(
+                "Invalid ip_version value '{}' encountered, defaulting to auto",
+                value
+            );
+            IpVersion::Auto
+        }
+    }
+}
+
+/// Address block in CIDR notation, e.g. `172.16.0.0/12`.
+#[derive(Debug, Clone, Copy)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u32,
+}
+
+impl IpRange {
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.network, addr) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (network, prefix_len) = value
+            .split_once('/')
+            .ok_or_else(|| format!("'{}' is not in address/prefix form", value))?;
+        let network: IpAddr = network
+            .parse()
+            .map_err(|_| format!("'{}' is not an IP address", network))?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        match prefix_len.parse() {
+            Ok(prefix_len) if prefix_len <= max_len => Ok(IpRange {
+                network,
+                prefix_len,
+            }),
+            _ => Err(format!(
+                "'{}' is not a prefix length between 0 and {}",
+                prefix_len, max_len
+            )),
+        }
+    }
+}
+
+/// Address to show for this host, read from the kernel's interface list.
+/// Interfaces are checked in name order so the pick is stable between runs;
+/// `interface` restricts the search to a single interface. Addresses in
+/// `skip` ranges, e.g. docker bridges, are only shown when there is no other.
+pub fn primary_ip(interface: Option<&str>, version: IpVersion, skip: &[IpRange]) -> Option<IpAddr> {
+    let networks = Networks::new_with_refreshed_list();
+    let mut names: Vec<&String> = networks
+        .list()
+        .keys()
+        .filter(|name| interface.is_none_or(|wanted| wanted == name.as_str()))
+        .collect();
+    names.sort();
+
+    let addrs: Vec<IpAddr> = names
+        .into_iter()
+        .flat_map(|name| {
+            networks.list()[name]
+                .ip_networks()
+                .iter()
+                .map(|net| net.addr)
+        })
+        .collect();
+    let skipped = |addr: IpAddr| skip.iter().any(|range| range.contains(&addr));
+    // A link-local address only means DHCP failed; anything else wins.
+    let preferred_v4 = |addr: &Ipv4Addr| !addr.is_link_local() && !skipped(IpAddr::V4(*addr));
+    // Prefer globally routable addresses over unique local (fc00::/7) ones.
+    let preferred_v6 =
+        |addr: &Ipv6Addr| (addr.segments()[0] & 0xfe00) != 0xfc00 && !skipped(IpAddr::V6(*addr));
+    let v4 = || {
+        let candidates: Vec<Ipv4Addr> = addrs
+            .iter()
+            .filter_map(|addr| match addr {
+                IpAddr::V4(addr) if !addr.is_loopback() && !addr.is_unspecified() => Some(*addr),
+                _ => None,
+            })
+            .collect();
+        candidates
+            .iter()
+            .find(|addr| preferred_v4(addr))
+            .or(candidates.first())
+            .copied()
+    };
+    let v6 = || {
+        let candidates: Vec<Ipv6Addr> = addrs
+            .iter()
+            .filter_map(|addr| match addr {
+                IpAddr::V6(addr) if is_routable_v6(addr) => Some(*addr),
+                _ => None,
+            })
+            .collect();
+        candidates
+            .iter()
+            .find(|addr| preferred_v6(addr))
+            .or(candidates.first())
+            .copied()
+    };
+
+    match version {
+        IpVersion::V4 => v4().map(IpAddr::V4),
+        IpVersion::V6 => v6().map(IpAddr::V6),
+        IpVersion::Auto => match (v4(), v6()) {
+            (Some(addr), _) if preferred_v4(&addr) => Some(IpAddr::V4(addr)),
+            (_, Some(addr)) if preferred_v6(&addr) => Some(IpAddr::V6(addr)),
+            (Some(addr), _) if !addr.is_link_local() => Some(IpAddr::V4(addr)),
+            (link_local, v6) => v6.map(IpAddr::V6).or(link_local.map(IpAddr::V4)),
+        },
+    }
+}
+
+const IPV4_ROUTE_PATH: &str = "/proc/net/route";
+const IPV6_ROUTE_PATH: &str = "/proc/net/ipv6_route";
+/// `RTF_GATEWAY`: the route goes through a router.
+const ROUTE_FLAG_GATEWAY: u32 = 0x2;
+
+/// Router of the default route with the lowest metric, from the kernel's
+/// routing tables. `Auto` looks for an IPv4 route first.
+pub fn default_gateway(version: IpVersion) -> Option<IpAddr> {
+    match version {
+        IpVersion::V4 => default_gateway_v4().map(IpAddr::V4),
+        IpVersion::V6 => default_gateway_v6().map(IpAddr::V6),
+        IpVersion::Auto => default_gateway_v4()
+            .map(IpAddr::V4)
+            .or_else(|| default_gateway_v6().map(IpAddr::V6)),
+    }
+}
+
+/// Lines are `Iface Destination Gateway Flags RefCnt Use Metric Mask ...`
+/// with the addresses as little-endian hex, e.g. `010200C0` for 192.0.2.1.
+fn default_gateway_v4() -> Option<Ipv4Addr> {
+    let table = fs::read_to_string(IPV4_ROUTE_PATH).ok()?;
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            if *fields.get(1)? != "00000000" || flags & ROUTE_FLAG_GATEWAY == 0 {
+                return None;
+            }
+            let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+            let metric: u32 = fields.get(6)?.parse().ok()?;
+            Some((metric, Ipv4Addr::from(gateway.to_le_bytes())))
+        })
+        .min_by_key(|&(metric, _)| metric)
+        .map(|(_, gateway)| gateway)
+}
+
+/// Lines are `Destination PrefixLen Source PrefixLen NextHop Metric ...`
+/// with the addresses as 32 hex digits.
+fn default_gateway_v6() -> Option<Ipv6Addr> {
+    let table = fs::read_to_string(IPV6_ROUTE_PATH).ok()?;
+    table
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if u128::from_str_radix(fields.first()?, 16).ok()? != 0 || *fields.get(1)? != "00" {
+                return None;
+            }
+            let next_hop = Ipv6Addr::from(u128::from_str_radix(fields.get(4)?, 16).ok()?);
+            let metric = u32::from_str_radix(fields.get(5)?, 16).ok()?;
+            (!next_hop.is_unspecified()).then_some((metric, next_hop))
+        })
+        .min_by_key(|&(metric, _)| metric)
+        .map(|(_, next_hop)| next_hop)
+}
+
+/// Not loopback, unspecified or link-local (fe80::/10).
+fn is_routable_v6(addr: &Ipv6Addr) -> bool {
+    !addr.is_loopback() && !addr.is_unspecified() && (addr.segments()[0] & 0xffc0) != 0xfe80
+}
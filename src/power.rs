@@ -0,0 +1,78 @@
+use crate::config::PowerConfig;
+use linux_embedded_hal::i2cdev::core::I2CDevice;
+use linux_embedded_hal::i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use log::{info, warn};
+
+const SHUNT_VOLTAGE_REGISTER: u8 = 0x01;
+const BUS_VOLTAGE_REGISTER: u8 = 0x02;
+/// Volts per bit of the shunt voltage register.
+const SHUNT_VOLTAGE_LSB: f32 = 0.000_01;
+/// Volts per bit of the bus voltage, which sits in bits 15-3.
+const BUS_VOLTAGE_LSB: f32 = 0.004;
+
+pub struct PowerReading {
+    pub voltage: f32,
+    pub current: f32,
+    pub watts: f32,
+}
+
+/// INA219 current and voltage monitor. The current is computed from the
+/// shunt voltage, so the chip's calibration register is left alone.
+pub struct PowerSensor {
+    device: LinuxI2CDevice,
+    shunt_ohms: f32,
+}
+
+impl PowerSensor {
+    /// Opens the sensor and takes a first reading. Returns `None` when no
+    /// sensor answers at the address, so the monitor runs without it.
+    pub fn new(power_config: &PowerConfig, address: u8) -> Option<Self> {
+        let device = match LinuxI2CDevice::new(&power_config.i2c_bus, address.into()) {
+            Ok(device) => device,
+            Err(e) => {
+                warn!(
+                    "Failed to open {} for the power sensor: {}. Continuing without it.",
+                    power_config.i2c_bus, e
+                );
+                return None;
+            }
+        };
+        let mut sensor = PowerSensor {
+            device,
+            shunt_ohms: power_config.shunt_ohms,
+        };
+        match sensor.read() {
+            Ok(reading) => {
+                info!(
+                    "INA219 power sensor at 0x{:02X}: {:.2}V, {:.2}W",
+                    address, reading.voltage, reading.watts
+                );
+                Some(sensor)
+            }
+            Err(e) => {
+                warn!(
+                    "No power sensor at 0x{:02X} on {}: {}. Continuing without it.",
+                    address, power_config.i2c_bus, e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn read(&mut self) -> Result<PowerReading, LinuxI2CError> {
+        let shunt_voltage =
+            self.read_register(SHUNT_VOLTAGE_REGISTER)? as i16 as f32 * SHUNT_VOLTAGE_LSB;
+        let voltage = (self.read_register(BUS_VOLTAGE_REGISTER)? >> 3) as f32 * BUS_VOLTAGE_LSB;
+        let current = shunt_voltage / self.shunt_ohms;
+        Ok(PowerReading {
+            voltage,
+            current,
+            watts: voltage * current,
+        })
+    }
+
+    /// SMBus words are little-endian, the INA219 sends the high byte first.
+    fn read_register(&mut self, register: u8) -> Result<u16, LinuxI2CError> {
+        Ok(self.device.smbus_read_word_data(register)?.swap_bytes())
+    }
+}
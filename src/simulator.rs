@@ -0,0 +1,265 @@
+use crate::config::TemperatureConfig;
+use crate::display_types::PanelDriver;
+use crate::network::{IpRange, IpVersion};
+use crate::stats_provider::{StatsProvider, SystemProvider};
+use display_interface::DisplayError;
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use log::{debug, info, warn};
+use ssd1306::prelude::Brightness;
+use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+const TEMP_ENV_VAR: &str = "RUSTBERRY_SIM_TEMP";
+const HOSTNAME_ENV_VAR: &str = "RUSTBERRY_SIM_HOSTNAME";
+const IP_ENV_VAR: &str = "RUSTBERRY_SIM_IP";
+const RAM_ENV_VAR: &str = "RUSTBERRY_SIM_RAM";
+const TEMP_RAMP_MIN: f32 = 40.0;
+const TEMP_RAMP_MAX: f32 = 75.0;
+const TEMP_RAMP_PERIOD_SECS: f32 = 120.0;
+
+/// Stand-in for the sensors while running with `--simulate`. The other
+/// readings are the host's, except for the hostname, IP address and RAM
+/// usage when fixed with `RUSTBERRY_SIM_HOSTNAME`, `RUSTBERRY_SIM_IP` and
+/// `RUSTBERRY_SIM_RAM`, so frames can be compared between runs.
+pub struct Simulation {
+    system: SystemProvider,
+    start: Instant,
+    fixed_temp: Option<f32>,
+    fixed_hostname: Option<String>,
+    fixed_ip: Option<String>,
+    fixed_ram: Option<f64>,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        let fixed_temp = parse_env(TEMP_ENV_VAR);
+        match fixed_temp {
+            Some(temp) => info!("Simulating a CPU temperature of {:.1}°C", temp),
+            None => info!(
+                "Simulating a CPU temperature between {:.0} and {:.0}°C, set {} for a fixed value",
+                TEMP_RAMP_MIN, TEMP_RAMP_MAX, TEMP_ENV_VAR
+            ),
+        }
+        Simulation {
+            system: SystemProvider::new(),
+            start: Instant::now(),
+            fixed_temp,
+            fixed_hostname: env::var(HOSTNAME_ENV_VAR).ok(),
+            fixed_ip: parse_env::<IpAddr>(IP_ENV_VAR).map(|ip| ip.to_string()),
+            fixed_ram: parse_env(RAM_ENV_VAR),
+        }
+    }
+}
+
+/// Value of the environment variable `name`; `None`, with a warning when
+/// set, when it does not parse.
+fn parse_env<T: FromStr>(name: &str) -> Option<T> {
+    let value = env::var(name).ok()?;
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        warn!("Ignoring invalid {}={:?}", name, value);
+    }
+    parsed
+}
+
+impl StatsProvider for Simulation {
+    fn refresh(&mut self) {
+        self.system.refresh();
+    }
+
+    fn hostname(&self) -> String {
+        self.fixed_hostname
+            .clone()
+            .unwrap_or_else(|| self.system.hostname())
+    }
+
+    fn ip_address(&self, interface: Option<&str>, version: IpVersion, skip: &[IpRange]) -> String {
+        self.fixed_ip
+            .clone()
+            .unwrap_or_else(|| self.system.ip_address(interface, version, skip))
+    }
+
+    fn gateway(&self, version: IpVersion) -> Option<IpAddr> {
+        self.system.gateway(version)
+    }
+
+    /// `RUSTBERRY_SIM_TEMP` when set, otherwise a ramp up and back down every
+    /// two minutes, so the fan thresholds are crossed.
+    fn cpu_temperature(&self, _temp_config: &TemperatureConfig) -> Option<f32> {
+        if let Some(temp) = self.fixed_temp {
+            return Some(temp);
+        }
+        let phase = (self.start.elapsed().as_secs_f32() / TEMP_RAMP_PERIOD_SECS).fract();
+        let ramp = 1.0 - (2.0 * phase - 1.0).abs();
+        Some(TEMP_RAMP_MIN + ramp * (TEMP_RAMP_MAX - TEMP_RAMP_MIN))
+    }
+
+    fn gpu_temperature(&self) -> Option<f32> {
+        self.system.gpu_temperature()
+    }
+
+    fn throttle_status(&self) -> Option<Vec<String>> {
+        self.system.throttle_status()
+    }
+
+    fn cpu_usage(&self) -> f32 {
+        self.system.cpu_usage()
+    }
+
+    fn cpu_per_core(&self) -> Vec<f32> {
+        self.system.cpu_per_core()
+    }
+
+    fn cpu_frequency(&self) -> Option<u32> {
+        self.system.cpu_frequency()
+    }
+
+    /// With `RUSTBERRY_SIM_RAM`, that percentage of the host's RAM.
+    fn memory(&self) -> (u64, u64) {
+        let (used, total) = self.system.memory();
+        match self.fixed_ram {
+            Some(percent) => ((total as f64 * percent / 100.0) as u64, total),
+            None => (used, total),
+        }
+    }
+
+    fn swap_usage(&self) -> Option<f64> {
+        self.system.swap_usage()
+    }
+
+    fn disk_space(&self, mount_point: &str) -> (u64, u64) {
+        self.system.disk_space(mount_point)
+    }
+
+    fn uptime(&self) -> u64 {
+        self.system.uptime()
+    }
+
+    fn load_average(&self) -> [f64; 3] {
+        self.system.load_average()
+    }
+
+    fn is_simulated(&self) -> bool {
+        true
+    }
+}
+
+/// Frame buffer standing in for a panel. Each flush writes the frame to a
+/// PBM image, lit pixels white, which image viewers that reload on change
+/// (e.g. `feh --reload 1`) show like the panel would.
+pub struct SimulatedPanel {
+    size: Size,
+    pixels: Vec<bool>,
+    /// Frame of the last flush, as the panel would show it.
+    shown: Vec<bool>,
+    path: PathBuf,
+    display_on: bool,
+    invert: bool,
+}
+
+impl SimulatedPanel {
+    pub fn new(
+        dir: &Path,
+        address: u8,
+        size: Size,
+        invert: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create frame directory {:?}: {}", dir, e))?;
+        let path = dir.join(format!("display-0x{:02X}.pbm", address));
+        info!(
+            "Simulated display 0x{:02X} writes frames to {:?}",
+            address, path
+        );
+        let pixel_count = (size.width * size.height) as usize;
+        Ok(SimulatedPanel {
+            size,
+            pixels: vec![false; pixel_count],
+            shown: vec![false; pixel_count],
+            path,
+            display_on: true,
+            invert,
+        })
+    }
+
+    /// Writes the shown frame as a binary PBM, which holds the one-bit pixels
+    /// as they are and needs no encoder. The image is written next to the
+    /// target and renamed, so viewers never pick up half a frame.
+    fn write_frame(&self) {
+        let mut data = format!("P4\n{} {}\n", self.size.width, self.size.height).into_bytes();
+        for row in self.shown.chunks(self.size.width as usize) {
+            for byte in row.chunks(8) {
+                // PBM uses 1 for black.
+                let bits = byte.iter().enumerate().fold(0u8, |bits, (i, &lit)| {
+                    if self.display_on && lit != self.invert {
+                        bits
+                    } else {
+                        bits | 0x80 >> i
+                    }
+                });
+                data.push(bits);
+            }
+        }
+        let tmp_path = self.path.with_extension("pbm.tmp");
+        if let Err(e) = fs::write(&tmp_path, &data).and_then(|()| fs::rename(&tmp_path, &self.path))
+        {
+            warn!("Failed to write frame to {:?}: {}", self.path, e);
+        }
+    }
+}
+
+impl PanelDriver for SimulatedPanel {
+    fn flush(&mut self) -> Result<(), DisplayError> {
+        self.shown.copy_from_slice(&self.pixels);
+        self.write_frame();
+        Ok(())
+    }
+
+    fn set_brightness(&mut self, brightness: Brightness) -> Result<(), DisplayError> {
+        debug!("Simulated display brightness {:?}", brightness);
+        Ok(())
+    }
+
+    fn set_display_on(&mut self, on: bool) -> Result<(), DisplayError> {
+        if on != self.display_on {
+            self.display_on = on;
+            self.write_frame();
+        }
+        Ok(())
+    }
+
+    fn set_invert(&mut self, invert: bool) -> Result<(), DisplayError> {
+        self.invert = invert;
+        self.write_frame();
+        Ok(())
+    }
+}
+
+impl OriginDimensions for SimulatedPanel {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for SimulatedPanel {
+    type Color = BinaryColor;
+    type Error = DisplayError;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.bounding_box();
+        for Pixel(point, color) in pixels {
+            if bounds.contains(point) {
+                let index = point.y as usize * self.size.width as usize + point.x as usize;
+                self.pixels[index] = color.is_on();
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use crate::config::LoggingConfig;
+use crate::SystemStats;
+use log::{debug, info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const CSV_HEADER: &str = "timestamp,cpu_usage,cpu_temp,ram_usage,fan_running";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Csv,
+    JsonLines,
+}
+
+/// Appends a stats sample to a CSV or JSON-lines file every `interval_secs`.
+/// Once the file grows past `max_size_kb` it is moved to `<file>.1`
+/// (replacing the previous one) and a fresh file is started, so at most two
+/// files' worth of samples are kept.
+pub struct StatsLogger {
+    path: PathBuf,
+    format: Format,
+    interval: Duration,
+    max_size: u64,
+    last_write: Option<Instant>,
+}
+
+impl StatsLogger {
+    pub fn new(logging_config: &LoggingConfig, file: &str) -> Self {
+        let format = match logging_config.format.as_str() {
+            "jsonl" => Format::JsonLines,
+            _ => Format::Csv,
+        };
+        info!("Logging stats to {} as {:?}", file, format);
+        StatsLogger {
+            path: PathBuf::from(file),
+            format,
+            interval: Duration::from_secs(logging_config.interval_secs),
+            max_size: logging_config.max_size_kb * 1024,
+            last_write: None,
+        }
+    }
+
+    /// Writes a sample if `interval_secs` have passed since the last one.
+    /// Write errors are logged and the sample is dropped.
+    pub fn record(&mut self, now: Instant, stats: &SystemStats, fan_running: bool) {
+        if self
+            .last_write
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return;
+        }
+        self.last_write = Some(now);
+        if let Err(e) = self.write_sample(stats, fan_running) {
+            warn!("Failed to write stats log {:?}: {}", self.path, e);
+        }
+    }
+
+    fn write_sample(&self, stats: &SystemStats, fan_running: bool) -> io::Result<()> {
+        self.rotate_if_full()?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let line = match self.format {
+            Format::Csv => format!(
+                "{},{:.1},{:.1},{:.1},{}",
+                timestamp, stats.cpu_usage, stats.cpu_temp, stats.ram_usage, fan_running
+            ),
+            Format::JsonLines => {
+                let mut sample = serde_json::to_value(stats).map_err(io::Error::other)?;
+                sample["timestamp"] = timestamp.into();
+                sample["fan_running"] = fan_running.into();
+                sample.to_string()
+            }
+        };
+
+        let new_file = !self.path.exists();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        if new_file && self.format == Format::Csv {
+            writeln!(file, "{}", CSV_HEADER)?;
+        }
+        writeln!(file, "{}", line)
+    }
+
+    fn rotate_if_full(&self) -> io::Result<()> {
+        let size = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if self.max_size == 0 || size < self.max_size {
+            return Ok(());
+        }
+
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        debug!("Rotating stats log {:?} to {:?}", self.path, rotated);
+        fs::rename(&self.path, &rotated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn logger(name: &str, format: &str, max_size_kb: u64) -> (StatsLogger, PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "rustberry-stats-log-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut logging_config = Config::default().logging;
+        logging_config.format = format.to_string();
+        logging_config.interval_secs = 0;
+        logging_config.max_size_kb = max_size_kb;
+        let file = dir.join("stats.log");
+        (
+            StatsLogger::new(&logging_config, file.to_str().unwrap()),
+            dir,
+        )
+    }
+
+    fn sample_stats() -> SystemStats {
+        SystemStats {
+            cpu_usage: 12.34,
+            cpu_temp: 48.25,
+            ram_usage: 56.7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn csv_starts_with_header_and_appends_rows() {
+        let (mut logger, dir) = logger("csv", "csv", 0);
+        let now = Instant::now();
+        logger.record(now, &sample_stats(), true);
+        logger.record(now, &sample_stats(), false);
+
+        let contents = fs::read_to_string(dir.join("stats.log")).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        for (line, fan) in lines[1..].iter().zip(["true", "false"]) {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert!(fields[0].parse::<u64>().is_ok());
+            assert_eq!(fields[1..], ["12.3", "48.2", "56.7", fan]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jsonl_writes_one_object_per_sample() {
+        let (mut logger, dir) = logger("jsonl", "jsonl", 0);
+        let now = Instant::now();
+        logger.record(now, &sample_stats(), true);
+        logger.record(now, &sample_stats(), false);
+
+        let contents = fs::read_to_string(dir.join("stats.log")).unwrap();
+        let samples: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(samples.len(), 2);
+        assert!(samples[0]["timestamp"].is_u64());
+        assert_eq!(samples[0]["fan_running"], true);
+        assert_eq!(samples[1]["fan_running"], false);
+        assert!((samples[0]["cpu_usage"].as_f64().unwrap() - 12.34).abs() < 0.01);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn skips_samples_within_the_interval() {
+        let (mut logger, dir) = logger("interval", "csv", 0);
+        logger.interval = Duration::from_secs(60);
+        let now = Instant::now();
+        logger.record(now, &sample_stats(), true);
+        logger.record(now + Duration::from_secs(30), &sample_stats(), true);
+        logger.record(now + Duration::from_secs(60), &sample_stats(), true);
+
+        let contents = fs::read_to_string(dir.join("stats.log")).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_once_max_size_is_exceeded() {
+        let (mut logger, dir) = logger("rotate", "csv", 1);
+        let path = dir.join("stats.log");
+        let rotated = dir.join("stats.log.1");
+        let now = Instant::now();
+        while !rotated.exists() {
+            assert!(fs::metadata(&path).map_or(0, |m| m.len()) <= 1024 + 64);
+            logger.record(now, &sample_stats(), true);
+        }
+
+        let old = fs::read_to_string(&rotated).unwrap();
+        assert!(old.len() >= 1024);
+        assert!(old.starts_with(CSV_HEADER));
+        let new = fs::read_to_string(&path).unwrap();
+        assert_eq!(new.lines().count(), 2);
+        assert!(new.starts_with(CSV_HEADER));
+
+        // Later rotations replace `<file>.1` rather than piling up.
+        for _ in 0..100 {
+            logger.record(now, &sample_stats(), true);
+        }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,301 @@
+use crate::config::TemperatureConfig;
+use crate::network::{self, IpRange, IpVersion};
+use crate::throttle;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::Command;
+use sysinfo::{CpuRefreshKind, DiskRefreshKind, Disks, MemoryRefreshKind, RefreshKind, System};
+
+const CPU_FREQ_SYSFS_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_cur_freq";
+
+/// Where the host readings behind the stats come from, so the formatting,
+/// fan and display logic can run on made-up values, e.g. with `--simulate`.
+pub trait StatsProvider {
+    /// Re-reads the CPU, memory and disk usage; called once per refresh
+    /// before the other readings.
+    fn refresh(&mut self);
+
+    fn hostname(&self) -> String;
+
+    /// Displayed IP address, `0.0.0.0` when there is none.
+    fn ip_address(&self, interface: Option<&str>, version: IpVersion, skip: &[IpRange]) -> String;
+
+    /// Router of the default route, `None` without one.
+    fn gateway(&self, version: IpVersion) -> Option<IpAddr>;
+
+    /// CPU temperature in Celsius, `None` when there is no reading.
+    fn cpu_temperature(&self, temp_config: &TemperatureConfig) -> Option<f32>;
+
+    /// GPU temperature in Celsius, `None` when it cannot be read.
+    fn gpu_temperature(&self) -> Option<f32>;
+
+    /// Currently active throttling conditions, e.g. "Undervoltage", `None`
+    /// when they cannot be read.
+    fn throttle_status(&self) -> Option<Vec<String>>;
+
+    /// Overall CPU usage in percent.
+    fn cpu_usage(&self) -> f32;
+
+    /// CPU usage in percent of every core.
+    fn cpu_per_core(&self) -> Vec<f32>;
+
+    /// Current CPU clock in MHz, `None` when it cannot be read.
+    fn cpu_frequency(&self) -> Option<u32>;
+
+    /// Used and total RAM in bytes.
+    fn memory(&self) -> (u64, u64);
+
+    /// Swap usage in percent, `None` without swap.
+    fn swap_usage(&self) -> Option<f64>;
+
+    /// Used and total bytes of the filesystem mounted at `mount_point`.
+    fn disk_space(&self, mount_point: &str) -> (u64, u64);
+
+    /// Seconds since boot.
+    fn uptime(&self) -> u64;
+
+    /// The 1, 5 and 15 minute load averages.
+    fn load_average(&self) -> [f64; 3];
+
+    /// Whether the readings are not the host's, so nothing is to be done
+    /// about them (e.g. the critical temperature command is not run).
+    fn is_simulated(&self) -> bool {
+        false
+    }
+}
+
+/// Readings from the host itself.
+pub struct SystemProvider {
+    sys: System,
+    disks: Disks,
+}
+
+impl SystemProvider {
+    pub fn new() -> Self {
+        SystemProvider {
+            sys: System::new_with_specifics(
+                RefreshKind::nothing()
+                    .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+                    .with_memory(MemoryRefreshKind::nothing().with_ram()),
+            ),
+            disks: Disks::new_with_refreshed_list_specifics(
+                DiskRefreshKind::nothing().with_storage(),
+            ),
+        }
+    }
+}
+
+impl StatsProvider for SystemProvider {
+    fn refresh(&mut self) {
+        self.sys.refresh_cpu_usage();
+        self.sys.refresh_memory();
+        self.disks
+            .refresh_specifics(true, DiskRefreshKind::nothing().with_storage());
+    }
+
+    fn hostname(&self) -> String {
+        Command::new("hostname")
+            .output()
+            .ok()
+            .and_then(|output| {
+                if output.status.success() {
+                    String::from_utf8(output.stdout).ok()
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_else(|| "UNKNOWN".to_string())
+            .trim()
+            .to_string()
+    }
+
+    fn ip_address(&self, interface: Option<&str>, version: IpVersion, skip: &[IpRange]) -> String {
+        network::primary_ip(interface, version, skip)
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| crate::UNKNOWN_IP.to_string())
+    }
+
+    fn gateway(&self, version: IpVersion) -> Option<IpAddr> {
+        network::default_gateway(version)
+    }
+
+    fn cpu_temperature(&self, temp_config: &TemperatureConfig) -> Option<f32> {
+        crate::read_cpu_temperature(temp_config)
+    }
+
+    fn gpu_temperature(&self) -> Option<f32> {
+        crate::get_gpu_temperature()
+    }
+
+    fn throttle_status(&self) -> Option<Vec<String>> {
+        throttle::get_throttle_status()
+    }
+
+    fn cpu_usage(&self) -> f32 {
+        self.sys.global_cpu_usage()
+    }
+
+    fn cpu_per_core(&self) -> Vec<f32> {
+        self.sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect()
+    }
+
+    /// From cpufreq, which reports kHz, or else `vcgencmd measure_clock arm`,
+    /// which prints e.g. `frequency(48)=1800404352` in Hz.
+    fn cpu_frequency(&self) -> Option<u32> {
+        if let Ok(contents) = fs::read_to_string(CPU_FREQ_SYSFS_PATH) {
+            if let Ok(khz) = contents.trim().parse::<u32>() {
+                return Some(khz / 1000);
+            }
+        }
+        let output = Command::new("vcgencmd")
+            .args(["measure_clock", "arm"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (_, hz) = stdout.trim().split_once('=')?;
+        let hz: u64 = hz.parse().ok()?;
+        Some((hz / 1_000_000) as u32)
+    }
+
+    fn memory(&self) -> (u64, u64) {
+        (self.sys.used_memory(), self.sys.total_memory())
+    }
+
+    fn swap_usage(&self) -> Option<f64> {
+        let total_swap = self.sys.total_swap();
+        (total_swap > 0).then(|| self.sys.used_swap() as f64 / total_swap as f64 * 100.0)
+    }
+
+    fn disk_space(&self, mount_point: &str) -> (u64, u64) {
+        self.disks
+            .list()
+            .iter()
+            .find(|disk| disk.mount_point() == Path::new(mount_point))
+            .map(|disk| {
+                let total = disk.total_space();
+                (total.saturating_sub(disk.available_space()), total)
+            })
+            .unwrap_or((0, 0))
+    }
+
+    fn uptime(&self) -> u64 {
+        System::uptime()
+    }
+
+    fn load_average(&self) -> [f64; 3] {
+        let load = System::load_average();
+        [load.one, load.five, load.fifteen]
+    }
+}
+
+/// Fixed readings, so tests of the formatting and the fan and display logic
+/// do not depend on the machine they run on.
+#[cfg(test)]
+pub struct MockProvider {
+    pub hostname: String,
+    pub ip_address: String,
+    pub gateway: Option<IpAddr>,
+    pub cpu_temp: Option<f32>,
+    pub gpu_temp: Option<f32>,
+    pub throttle_status: Option<Vec<String>>,
+    pub cpu_usage: f32,
+    pub cpu_per_core: Vec<f32>,
+    pub cpu_freq: Option<u32>,
+    pub memory: (u64, u64),
+    pub swap_usage: Option<f64>,
+    pub disk_space: (u64, u64),
+    pub uptime: u64,
+    pub load_average: [f64; 3],
+}
+
+#[cfg(test)]
+impl Default for MockProvider {
+    fn default() -> Self {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        MockProvider {
+            hostname: "raspberrypi".to_string(),
+            ip_address: "192.168.1.42".to_string(),
+            gateway: Some(IpAddr::from([192, 168, 1, 1])),
+            cpu_temp: Some(48.2),
+            gpu_temp: Some(47.1),
+            throttle_status: Some(Vec::new()),
+            cpu_usage: 12.3,
+            cpu_per_core: vec![10.0, 20.0, 5.0, 14.2],
+            cpu_freq: Some(1800),
+            memory: (GIB, 4 * GIB),
+            swap_usage: None,
+            disk_space: (10 * GIB, 40 * GIB),
+            uptime: 3 * 86400 + 4 * 3600 + 5 * 60,
+            load_average: [0.52, 0.41, 0.3],
+        }
+    }
+}
+
+#[cfg(test)]
+impl StatsProvider for MockProvider {
+    fn refresh(&mut self) {}
+
+    fn hostname(&self) -> String {
+        self.hostname.clone()
+    }
+
+    fn ip_address(
+        &self,
+        _interface: Option<&str>,
+        _version: IpVersion,
+        _skip: &[IpRange],
+    ) -> String {
+        self.ip_address.clone()
+    }
+
+    fn gateway(&self, _version: IpVersion) -> Option<IpAddr> {
+        self.gateway
+    }
+
+    fn cpu_temperature(&self, _temp_config: &TemperatureConfig) -> Option<f32> {
+        self.cpu_temp
+    }
+
+    fn gpu_temperature(&self) -> Option<f32> {
+        self.gpu_temp
+    }
+
+    fn throttle_status(&self) -> Option<Vec<String>> {
+        self.throttle_status.clone()
+    }
+
+    fn cpu_usage(&self) -> f32 {
+        self.cpu_usage
+    }
+
+    fn cpu_per_core(&self) -> Vec<f32> {
+        self.cpu_per_core.clone()
+    }
+
+    fn cpu_frequency(&self) -> Option<u32> {
+        self.cpu_freq
+    }
+
+    fn memory(&self) -> (u64, u64) {
+        self.memory
+    }
+
+    fn swap_usage(&self) -> Option<f64> {
+        self.swap_usage
+    }
+
+    fn disk_space(&self, _mount_point: &str) -> (u64, u64) {
+        self.disk_space
+    }
+    fn uptime(&self) -> u64 {
+        self.uptime
+    }
+
+    fn load_average(&self) -> [f64; 3] {
+        self.load_average
+    }
+}
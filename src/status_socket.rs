@@ -0,0 +1,136 @@
+use crate::SystemStats;
+use log::{debug, info, warn};
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Writes the latest stats as one JSON object to every client connecting to
+/// a Unix domain socket, then closes the connection.
+pub struct StatusSocket {
+    path: PathBuf,
+    snapshot: Arc<Mutex<String>>,
+}
+
+impl StatusSocket {
+    /// Binds the socket, replacing a stale socket left by a previous run, and
+    /// spawns the server thread. Returns `None` if the socket cannot be bound
+    /// or another kind of file is in the way, so the monitor keeps running
+    /// without it.
+    pub fn start(path: &str) -> Option<Self> {
+        let path = PathBuf::from(path);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_socket() => match fs::remove_file(&path) {
+                Ok(()) => debug!("Removed stale status socket {:?}", path),
+                Err(e) => warn!("Failed to remove stale status socket {:?}: {}", path, e),
+            },
+            Ok(_) => {
+                warn!(
+                    "{:?} exists and is not a socket, leaving it alone. Continuing without \
+                     the status socket.",
+                    path
+                );
+                return None;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to check status socket path {:?}: {}", path, e),
+        }
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!(
+                    "Failed to bind status socket {:?}: {}. Continuing without it.",
+                    path, e
+                );
+                return None;
+            }
+        };
+        info!("Status socket listening at {:?}", path);
+
+        let snapshot = Arc::new(Mutex::new("{}".to_string()));
+        let server_snapshot = Arc::clone(&snapshot);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(e) = handle_connection(stream, &server_snapshot) {
+                            debug!("Status socket connection error: {}", e);
+                        }
+                    }
+                    Err(e) => debug!("Failed to accept status socket connection: {}", e),
+                }
+            }
+        });
+
+        Some(StatusSocket { path, snapshot })
+    }
+
+    pub fn update(&self, stats: &SystemStats, fan_running: bool) {
+        let mut status = match serde_json::to_value(stats) {
+            Ok(status) => status,
+            Err(e) => {
+                warn!("Failed to serialize stats for the status socket: {}", e);
+                return;
+            }
+        };
+        status["fan_running"] = fan_running.into();
+        *self.snapshot.lock().unwrap() = status.to_string();
+    }
+}
+
+impl Drop for StatusSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn handle_connection(mut stream: UnixStream, snapshot: &Mutex<String>) -> io::Result<()> {
+    let status = snapshot.lock().unwrap().clone();
+    writeln!(stream, "{}", status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "rustberry-status-test-{}-{}.sock",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn keeps_a_regular_file_at_the_path() {
+        let path = socket_path("regular-file");
+        fs::write(&path, "not a socket").unwrap();
+        assert!(StatusSocket::start(path.to_str().unwrap()).is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not a socket");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn replaces_a_stale_socket() {
+        let path = socket_path("stale");
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let socket = StatusSocket::start(path.to_str().unwrap()).unwrap();
+        socket.update(&SystemStats::default(), true);
+        let mut status = String::new();
+        UnixStream::connect(&path)
+            .unwrap()
+            .read_to_string(&mut status)
+            .unwrap();
+        let status: serde_json::Value = serde_json::from_str(&status).unwrap();
+        assert_eq!(status["fan_running"], true);
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,67 @@
+use log::{debug, info, warn};
+use std::env;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::process;
+
+/// Sends `sd_notify` messages to systemd. Only active when the service
+/// manager set `NOTIFY_SOCKET`, so running outside systemd is unaffected.
+pub struct Notifier {
+    socket: UnixDatagram,
+    addr: SocketAddr,
+    watchdog: bool,
+}
+
+impl Notifier {
+    pub fn from_env() -> Option<Self> {
+        let path = env::var("NOTIFY_SOCKET").ok()?;
+        let socket = match UnixDatagram::unbound() {
+            Ok(socket) => socket,
+            Err(e) => {
+                warn!("Failed to create systemd notify socket: {}", e);
+                return None;
+            }
+        };
+        // A leading '@' marks a socket in the abstract namespace.
+        let addr = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(&path),
+        };
+        let addr = match addr {
+            Ok(addr) => addr,
+            Err(e) => {
+                warn!("Invalid NOTIFY_SOCKET {}: {}", path, e);
+                return None;
+            }
+        };
+        let watchdog = env::var("WATCHDOG_USEC").is_ok()
+            && env::var("WATCHDOG_PID").map_or(true, |pid| pid.parse() == Ok(process::id()));
+        info!(
+            "systemd notifications enabled{}",
+            if watchdog { " with watchdog" } else { "" }
+        );
+        Some(Notifier {
+            socket,
+            addr,
+            watchdog,
+        })
+    }
+
+    /// Tells systemd that startup has finished.
+    pub fn ready(&self) {
+        self.send("READY=1");
+    }
+
+    /// Resets the watchdog timer if `WatchdogSec=` is configured.
+    pub fn watchdog(&self) {
+        if self.watchdog {
+            self.send("WATCHDOG=1");
+        }
+    }
+
+    fn send(&self, message: &str) {
+        if let Err(e) = self.socket.send_to_addr(message.as_bytes(), &self.addr) {
+            debug!("Failed to send {} to systemd: {}", message, e);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use log::trace;
+use std::fs;
+use std::process::Command;
+
+const THROTTLED_SYSFS_PATH: &str = "/sys/devices/platform/soc/soc:firmware/get_throttled";
+
+/// Bits of the firmware's `get_throttled` value that describe the current
+/// state; the upper bits only record that a condition occurred since boot.
+const ACTIVE_FLAGS: [(u32, &str); 4] = [
+    (1 << 0, "Undervoltage"),
+    (1 << 1, "Freq capped"),
+    (1 << 2, "Throttled"),
+    (1 << 3, "Soft temp limit"),
+];
+
+/// Currently active throttling conditions, empty when nothing is active.
+/// `None` when neither the sysfs file nor `vcgencmd` is available.
+pub fn get_throttle_status() -> Option<Vec<String>> {
+    let flags = read_throttled()?;
+    trace!("Throttled flags: {:#x}", flags);
+    Some(
+        ACTIVE_FLAGS
+            .iter()
+            .filter(|(bit, _)| flags & bit != 0)
+            .map(|(_, name)| name.to_string())
+            .collect(),
+    )
+}
+
+fn read_throttled() -> Option<u32> {
+    if let Ok(contents) = fs::read_to_string(THROTTLED_SYSFS_PATH) {
+        return u32::from_str_radix(contents.trim().trim_start_matches("0x"), 16).ok();
+    }
+
+    // `vcgencmd get_throttled` prints e.g. `throttled=0x50005`.
+    let output = Command::new("vcgencmd")
+        .arg("get_throttled")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let value = stdout.trim().strip_prefix("throttled=")?;
+    u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}